@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use twitch_irc::message::PrivmsgMessage;
+use crate::bots::REGISTRY;
+use crate::util::{bot::{Bot, GlobalState, Handled}, command::{self, Command, Cooldowns, Permission}, metrics};
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "!commands",
+        aliases: &[],
+        usage: "!commands",
+        description: "List the commands you can use.",
+        permission: Permission::Everyone,
+        cooldown: Some(10),
+    },
+    Command {
+        name: "!help",
+        aliases: &[],
+        usage: "!help <command>",
+        description: "Explain what a command does.",
+        permission: Permission::Everyone,
+        cooldown: Some(5),
+    },
+];
+
+pub struct HelpBot {
+    pub bot_is_enabled: bool,
+    pub cooldowns: Cooldowns,
+}
+
+impl Default for HelpBot {
+    fn default() -> Self {
+        Self { bot_is_enabled: true, cooldowns: Default::default() }
+    }
+}
+
+#[async_trait]
+impl Bot for HelpBot {
+    fn name(&self) -> &str {
+        "HelpBot"
+    }
+
+    fn is_enabled(&mut self) -> bool {
+        self.bot_is_enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.bot_is_enabled = enabled;
+    }
+
+    async fn handle_message(&mut self, global_state: &GlobalState, msg: &PrivmsgMessage) -> Handled {
+        match command::find(COMMANDS, msg) {
+            Some(command) => {
+                if self.cooldowns.ready(command) {
+                    metrics::command_handled(self.name(), command.name);
+                    self.run_command(command.name, global_state, msg).await;
+                }
+                Handled::Consumed
+            },
+            None => Handled::Passed,
+        }
+    }
+
+    async fn update(&mut self, _global_state: &GlobalState) {}
+
+    async fn start_poll(&mut self, _global_state: &GlobalState) {}
+
+    async fn stop_poll(&mut self, _global_state: &GlobalState) {}
+}
+
+impl HelpBot {
+    /** Run one of this bot's `COMMANDS`, the caller is allowed to run it */
+    async fn run_command(&mut self, name: &str, global_state: &GlobalState, msg: &PrivmsgMessage) {
+        match name {
+            "!commands" => {
+                let names: Vec<&str> = REGISTRY.iter()
+                    .flat_map(|(_, commands)| commands.iter())
+                    .filter(|command| command.is_allowed(msg))
+                    .map(|command| command.name)
+                    .collect();
+                global_state.outbox.say([
+                    "@".to_owned(),
+                    msg.sender.name.clone(),
+                    " Commands: ".to_owned(),
+                    names.join(", "),
+                ].concat());
+            }
+            "!help" => {
+                let args = command::args(msg);
+                let message = match args.first() {
+                    Some(wanted) => {
+                        let found = REGISTRY.iter()
+                            .flat_map(|(_, commands)| commands.iter())
+                            .find(|command| command.matches(wanted));
+                        match found {
+                            Some(command) => command.help_line(),
+                            None => ["There's no command called ".to_owned(), wanted.to_string()].concat(),
+                        }
+                    },
+                    None => "Usage: !help <command>, use !commands to see them all".to_owned(),
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            _ => {}
+        }
+    }
+}
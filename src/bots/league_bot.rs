@@ -2,36 +2,130 @@ use std::{env, fmt::Display, io::Read, time::Duration};
 use inputbot::{KeySequence, KeybdKey};
 use tokio::time::sleep;
 //use std::{thread, time::{Duration}};
-use twitch_irc::message::{PrivmsgMessage, TwitchUserBasics};
+use twitch_irc::message::PrivmsgMessage;
 use async_trait::async_trait;
-use crate::util::{bot::{Bot, GlobalState}, twitch::is_mod, league::{LeagueResponse}};
+use serde::{Deserialize, Serialize};
+use crate::util::{bot::{Bot, GlobalState, Handled}, command::{self, Command, Cooldowns, Permission}, league::{LeagueResponse}, log, metrics};
+use crate::util::eligibility::{Eligibility, EligibilityRules};
+use crate::util::normalize::{vote_candidates, Strictness};
+use crate::util::poll::{Choice, Poll, Veto};
+use crate::util::history::PollRecord;
+use crate::util::tally::{self, Outcome, TiePolicy};
+use crate::display::PollView;
 
-#[derive(Clone, Copy)]
-// Q, W, E, R
-pub struct Votes (i32, i32, i32, i32);
-
-impl Votes {
-    fn most_voted(& self) -> Option<Poggers> {
-        let values = [self.0, self.1, self.2, self.3];
-        let max = values.iter().max().unwrap();
-        if let Some(index_of_max) = values.iter().position(|&x| x == *max) {
-            match index_of_max {
-                0 => Some(Poggers::Q),
-                1 => Some(Poggers::W),
-                2 => Some(Poggers::E),
-                3 => Some(Poggers::R),
-                _ => None
-            }
-        } else {
-            return None;
+/** The `[league_bot]` section of the config */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LeagueBotConfig {
+    /** What happens when the most voted abilities are tied */
+    #[serde(default)]
+    pub tie_policy: TiePolicy,
+    /** Ability the `default` tie policy levels, 1 to 4 for Q to R */
+    #[serde(default = "default_tie_default")]
+    pub tie_default: usize,
+    /** How much of a message has to look like a vote */
+    #[serde(default)]
+    pub strictness: Strictness,
+    #[serde(default)]
+    pub eligibility: EligibilityRules,
+    /** Seconds mods get to `!veto_league` or `!override_league` the ability before it's leveled, off if 0 */
+    #[serde(default)]
+    pub confirm_seconds: i64,
+}
+
+fn default_tie_default() -> usize {
+    1
+}
+
+impl Default for LeagueBotConfig {
+    fn default() -> Self {
+        Self {
+            tie_policy: Default::default(),
+            tie_default: default_tie_default(),
+            strictness: Default::default(),
+            eligibility: Default::default(),
+            confirm_seconds: 0,
         }
     }
 }
 
-enum Poggers {
+const QUESTION: &str = "Level up an ability!";
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "!reset_league",
+        aliases: &["!up_league"],
+        usage: "!reset_league",
+        description: "Ask chat which ability to level up next.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!results_league",
+        aliases: &[],
+        usage: "!results_league",
+        description: "Stop counting and tell chat the results.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!stop_league",
+        aliases: &[],
+        usage: "!stop_league",
+        description: "Stop counting without telling the results.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!pick_league",
+        aliases: &["!pick"],
+        usage: "!pick_league <Q|W|E|R>",
+        description: "Break a tie when the tie policy lets the streamer decide.",
+        permission: Permission::Broadcaster,
+        cooldown: None,
+    },
+    Command {
+        name: "!veto_league",
+        aliases: &["!veto"],
+        usage: "!veto_league [rerun|runner_up]",
+        description: "Veto the ability before it's leveled, chat votes again or the runner-up gets leveled.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!override_league",
+        aliases: &["!override"],
+        usage: "!override_league <Q|W|E|R>",
+        description: "Level another ability than the one chat picked, before it's leveled.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!reconnect_league",
+        aliases: &[],
+        usage: "!reconnect_league",
+        description: "Try to connect to the league client again.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+];
+
+#[derive(Clone, Copy)]
+pub enum Poggers {
     Q,W,E,R
 }
 
+impl Poggers {
+    fn from_index(index: usize) -> Option<Poggers> {
+        match index {
+            0 => Some(Poggers::Q),
+            1 => Some(Poggers::W),
+            2 => Some(Poggers::E),
+            3 => Some(Poggers::R),
+            _ => None
+        }
+    }
+}
+
 impl Display for Poggers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", match self {
@@ -43,12 +137,19 @@ impl Display for Poggers {
     }
 }
 
+impl Choice for Poggers {
+    fn label(&self) -> String {
+        self.to_string()
+    }
+}
+
 pub struct State {
     // Voting Related Stuff
-    pub is_counting: bool,
-    pub voting_box: Votes,
-    pub who_voted: Vec<String>,
-    pub reset_timestamp: i64,
+    pub poll: Poll<Poggers>,
+    /** Ability the last vote ended up picking, ties included */
+    pub last_pick: Option<usize>,
+    /** Tied abilities the streamer picks from with `!pick_league` */
+    pub awaiting_pick: Vec<usize>,
     pub bot_is_enabled: bool,
     // League Client Related Stuff
     pub http_client: reqwest::Client,
@@ -68,51 +169,21 @@ pub struct State {
 
 impl State {
     pub fn to_string(&self) -> String {
-        let strings = [
-            self.voting_box.0.to_string(),
-            " Q, ".to_owned(),
-            self.voting_box.1.to_string(),
-            " W, ".to_owned(),
-            self.voting_box.2.to_string(),
-            " E, ".to_owned(),
-            self.voting_box.3.to_string(),
-            " R!".to_owned()
-        ];
-        strings.concat()
-    }
-    /** Reset the votes and start counting */
-    pub fn reset(&mut self) {
-        self.is_counting = true;
-        self.voting_box = Votes(0, 0, 0, 0);
-        self.who_voted = Vec::new();
-        self.reset_timestamp = chrono::offset::Local::now().timestamp_millis();
-    }
-    /** Stop counting */
-    pub fn stop_counting(&mut self) {
-        self.is_counting = false;
-    }
-    /** Add a vote to the box, the `to` is the index of the box to add to */
-    pub fn add_vote(&mut self, amount: i32, to: i8, voter: &TwitchUserBasics) {
-        match to {
-            0 => {
-                self.voting_box.0 = self.voting_box.0 + amount;
-            }
-            1 => {
-                self.voting_box.1 = self.voting_box.1 + amount;
-            }
-            2 => {
-                self.voting_box.2 = self.voting_box.2 + amount;
-            }
-            3 => {
-                self.voting_box.3 = self.voting_box.3 + amount;
-            }
-            _ => {}
+        let votes: Vec<String> = self.poll.options.iter()
+            .map(|option| [option.votes.to_string(), " ".to_owned(), option.label()].concat())
+            .collect();
+        let strings = [votes.join(", "), "!".to_owned()];
+        match self.poll.rejections.summary() {
+            Some(summary) => [strings.concat(), " (".to_owned(), summary, ")".to_owned()].concat(),
+            None => strings.concat(),
         }
-        self.who_voted.push(voter.id.clone())
     }
-    /** Returns true if a user has not voted */
-    pub fn can_vote(&self, voter: &TwitchUserBasics) -> bool {
-        self.is_counting && !self.who_voted.contains(&voter.id)
+    /** Reset the votes and start counting for 10 seconds */
+    pub fn reset(&mut self) {
+        self.poll.open(vec![Poggers::Q, Poggers::W, Poggers::E, Poggers::R], "LeagueBot");
+        self.poll.set_closes_at(Some(self.poll.opened_at + 10000), &[], &[]);
+        self.last_pick = None;
+        self.awaiting_pick = Vec::new();
     }
     /** 
     Compile a small string reading out the votebox into a message, if a msg is
@@ -139,6 +210,45 @@ impl State {
             }
         }
     }
+    /** Snapshot of the ability vote for the displays */
+    pub fn to_view(&self) -> PollView {
+        PollView {
+            bot: "LeagueBot".to_owned(),
+            question: QUESTION.to_owned(),
+            options: self.poll.option_views(),
+            is_open: self.poll.is_counting,
+            closes_at: if self.poll.is_counting { self.poll.closes_at } else { None },
+            winner: if self.poll.is_counting {
+                None
+            } else {
+                // Mods could have overridden the winner
+                let pick = self.last_pick.or(match self.poll.outcome() {
+                    Outcome::Winner(winner) => Some(winner),
+                    _ => None,
+                });
+                pick.and_then(|pick| self.poll.options.get(pick)).map(|option| option.label())
+            },
+            rating: None,
+            secret_voters: None,
+        }
+    }
+    /** The poll for the history, the winner is the most voted ability if there's one */
+    pub fn to_record(&self) -> PollRecord {
+        let winner = match self.poll.outcome() {
+            Outcome::Winner(winner) => self.poll.options.get(winner).map(|option| option.label()),
+            _ => None,
+        };
+        PollRecord::new(&self.poll, QUESTION, winner)
+    }
+    /** Short description of the league client connection for the dashboard */
+    pub fn connection_status(&self) -> String {
+        if self.http_client_connected {
+            let level = self.last_league_state.as_ref().map(|lls| lls.level).unwrap_or(0);
+            ["Connected, level ".to_owned(), level.to_string()].concat()
+        } else {
+            "Disconnected".to_owned()
+        }
+    }
     /** Resets the ff counter and it's associated timestamp */
     pub fn ff_reset(&mut self) {
         self.ff_counter = 0;
@@ -162,10 +272,9 @@ impl Default for State {
 
         let now = chrono::offset::Local::now().timestamp_millis();
         Self {
-            is_counting: false,
-            voting_box: Votes(0, 0, 0, 0),
-            who_voted: Vec::new(),
-            reset_timestamp: now.clone(),
+            poll: Poll::new("LeagueBot"),
+            last_pick: None,
+            awaiting_pick: Vec::new(),
             bot_is_enabled: true,
 
             http_client: client,
@@ -186,86 +295,115 @@ impl Default for State {
 
 pub struct LeagueBot {
     pub state: State,
+    pub chatters: Eligibility,
+    pub cooldowns: Cooldowns,
+    pub config: LeagueBotConfig,
 }
 
 impl Default for LeagueBot {
     fn default() -> Self {
-        Self { state: Default::default() }
+        Self {
+            state: Default::default(),
+            chatters: Default::default(),
+            cooldowns: Default::default(),
+            config: Default::default(),
+        }
+    }
+}
+
+impl LeagueBot {
+    pub fn new(config: LeagueBotConfig) -> Self {
+        let mut bot = Self { config, ..Default::default() };
+        bot.state.poll.eligibility = bot.config.eligibility.clone();
+        bot
     }
 }
 
 #[async_trait]
 impl Bot for LeagueBot {
+    fn name(&self) -> &str {
+        "LeagueBot"
+    }
+
+    /** Ability votes only last a few seconds, they go first */
+    fn priority(&self) -> i32 {
+        20
+    }
+
     fn is_enabled(&mut self) -> bool {
         self.state.bot_is_enabled
     }
 
-    async fn handle_message(&mut self, global_state: &GlobalState, client: &twitch_irc::TwitchIRCClient<twitch_irc::SecureTCPTransport, twitch_irc::login::StaticLoginCredentials>, msg: &PrivmsgMessage) {
-        match msg.message_text.to_uppercase().as_str() {
-            "Q" | "1" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 0, &msg.sender);
-                }
-            }
-            "W" | "2" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 1, &msg.sender);
-                }
-            }
-            "E" | "3" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 2, &msg.sender);
-                }
+    fn set_enabled(&mut self, enabled: bool) {
+        self.state.bot_is_enabled = enabled;
+    }
+
+    fn observe(&mut self, msg: &PrivmsgMessage) {
+        self.chatters.saw(msg);
+    }
+
+    async fn handle_message(&mut self, global_state: &GlobalState, msg: &PrivmsgMessage) -> Handled {
+        let owns_numbers = global_state.numbers.sync(self.name(), self.state.poll.is_counting);
+        let vote = vote_candidates(msg, self.config.strictness).iter()
+            .find_map(|text| match (text.to_uppercase().as_str(), owns_numbers) {
+                ("Q", _) | ("1", true) => Some(0),
+                ("W", _) | ("2", true) => Some(1),
+                ("E", _) | ("3", true) => Some(2),
+                ("R", _) | ("4", true) => Some(3),
+                _ => None,
+            });
+        if let Some(to) = vote {
+            if !self.state.poll.is_counting {
+                return Handled::Passed;
             }
-            "R" | "4" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 3, &msg.sender);
-                }
+            // Same order as VoteBot, ineligible voters get counted as rejected either way
+            if self.state.poll.check_eligibility(&self.chatters, msg, &global_state.helix)
+                && self.state.poll.can_vote(&msg.sender, &[to], false) {
+                self.state.poll.add_vote(1, vec![to], &msg.sender);
+                global_state.display.publish(self.state.to_view());
             }
+            return Handled::Consumed;
+        }
+        match msg.message_text.to_uppercase().as_str() {
             "FF" => {
                 self.state.ff_counter += 1;
+                Handled::Passed
             }
-            "!RESULTS_LEAGUE" => {
-                if is_mod(&msg) {
-                    self.state.stop_counting();
-                    let message = self.state.get_results_message(Some(msg), None);
-                    client.say(global_state.channel_name.to_owned(), message).await.unwrap();
-                }
-            }
-            "!UP_LEAGUE" |
-            "!RESET_LEAGUE" => {
-                if is_mod(&msg) {
-                    self.state.should_poll_for_level = true;
-                }
-            }
-            "!STOP_LEAGUE" => {
-                if is_mod(&msg) {
-                    self.state.stop_counting();
-                    client.say(
-                        global_state.channel_name.to_owned(), 
-                        "Stopped counting!".to_owned()
-                    ).await.unwrap();
-                }
-            }
-            "!RECONNECT_LEAGUE" => {
-                if is_mod(&msg) {
-                    self.state.http_client_attempt_connect = true;
-                    self.state.force_check_level = true;
+            _ => {
+                match command::find(COMMANDS, msg) {
+                    // `!pick`, `!veto` and `!override` are VoteBot's too, they're only for this bot while an ability waits on them
+                    Some(command) if !self.is_waiting_for(command, msg) => Handled::Passed,
+                    Some(command) => {
+                        if self.cooldowns.ready(command) {
+                            metrics::command_handled(self.name(), command.name);
+                            self.run_command(command.name, global_state, msg).await;
+                        }
+                        Handled::Consumed
+                    },
+                    None => Handled::Passed,
                 }
             }
-            _ => {}
         }
     }
 
-    async fn update(&mut self, global_state: &GlobalState, client: &twitch_irc::TwitchIRCClient<twitch_irc::SecureTCPTransport, twitch_irc::login::StaticLoginCredentials>) {
+    async fn update(&mut self, global_state: &GlobalState) {
         // Declare the current time
         let now = chrono::offset::Local::now().timestamp_millis();
+
+        // Votes that waited on Twitch go through the same checks again
+        for msg in std::mem::take(&mut self.state.poll.waiting) {
+            self.handle_message(global_state, &msg).await;
+        }
+
+        // Give the numbers back to the other polls once this one closes
+        global_state.numbers.sync(self.name(), self.state.poll.is_counting);
         
         // Check the client for automated leveling
         if self.state.http_client_attempt_connect {
             //println!("[LeagueBot] Connecting to the league client");
             self.update_league_client().await;
             //println!("[LeagueBot] Finnish");
+            global_state.display.set_status("League client", self.state.connection_status());
         }
         
         // Check if the league client is connected
@@ -274,36 +412,27 @@ impl Bot for LeagueBot {
             if self.state.force_check_level {
                 if let Some(lls) = &self.state.last_league_state {
                     self.state.last_level = lls.abilities.check_used_points();
-                    println!("[LeagueBot] Level was force checked, new level: {}", self.state.last_level);
+                    log::info(format!("[LeagueBot] Level was force checked, new level: {}", self.state.last_level));
                 }
                 self.state.force_check_level = false;
             }
 
             self.check_league_client().await;
 
-            // Check if more than 10 seconds have passed since started counting
-            if self.state.is_counting && (now - self.state.reset_timestamp > 10000 /* 10 sec in ms */) {
-                self.state.stop_counting();
-                let message = self.state.get_results_message(None, Some(&global_state.channel_name.clone().to_owned()));
-                client.say(global_state.channel_name.to_owned(), message).await.unwrap();
-
-                match self.state.voting_box.most_voted() {
-                    Some(vote) => {
-                        println!("[LeagueBot] Finished counting ability votes");
-                        tokio::spawn(LeagueBot::level_up_ability(vote));
-                        self.state.force_check_level = true;
-                    },
-                    None => {
-                        println!("[LeagueBot] No Votes lol gg vote again");
-                        self.state.reset();
-                    }
-                }
+            // Level the ability once mods had their chance to veto it
+            if let Some(pick) = self.state.poll.take_confirmed(now) {
+                self.confirm(pick, None, global_state);
+            }
+
+            // Check if the 10 seconds of counting are over
+            if self.state.poll.is_due(now) {
+                self.close_poll("timer", global_state);
             }
 
             // Check if a lot of people have voted to ff rather quickly
             if now - self.state.ff_reset_timestamp > 5000 /* 5 sec in ms */ {
                 if self.state.ff_counter > 20 {
-                    println!("[LeagueBot] Forcing FF vote");
+                    log::info("[LeagueBot] Forcing FF vote".to_owned());
                     tokio::spawn(LeagueBot::try_to_ff());
                     self.state.ff_reset();
                 }
@@ -311,18 +440,256 @@ impl Bot for LeagueBot {
 
             // Check if the bot should poll for level
             if self.state.should_poll_for_level {
+                // An ability still waiting for mods is final once chat votes again
+                if let Some(pending) = self.state.poll.pending.take() {
+                    self.confirm(pending.pick, None, global_state);
+                }
                 self.state.reset();
-                client.say(
-                    global_state.channel_name.to_owned(), 
-                    "Vote Q, W, E, R to level an ability!".to_owned()
-                ).await.unwrap();
+                global_state.display.publish(self.state.to_view());
+                global_state.outbox.say("Vote Q, W, E, R to level an ability!".to_owned());
                 self.state.should_poll_for_level = false;
             }
         }
     }
+
+    async fn start_poll(&mut self, _global_state: &GlobalState) {
+        self.state.should_poll_for_level = true;
+    }
+
+    async fn stop_poll(&mut self, global_state: &GlobalState) {
+        self.stop("dashboard", global_state);
+    }
 }
 
 impl LeagueBot {
+    /** Close the vote and level what chat picked, the timer and `!results_league` both end up here */
+    fn close_poll(&mut self, by: &str, global_state: &GlobalState) {
+        self.state.poll.close(by);
+        global_state.display.publish(self.state.to_view());
+        let message = self.state.get_results_message(None, Some(&global_state.channel_name));
+        global_state.outbox.say(message);
+
+        // Recorded before a runoff replaces the poll
+        let mut record = self.state.to_record();
+        self.state.last_pick = match self.state.poll.outcome() {
+            Outcome::Winner(winner) => Some(winner),
+            Outcome::Tie(tied) => self.break_tie(tied, global_state),
+            Outcome::NoVotes => {
+                // The level is still up for grabs so the next check asks chat again
+                log::info("[LeagueBot] No Votes lol gg vote again".to_owned());
+                None
+            },
+        };
+        match self.state.last_pick {
+            Some(pick) if self.config.confirm_seconds > 0 => {
+                self.state.poll.hold(pick, self.config.confirm_seconds);
+                global_state.outbox.say([
+                    "Leveling ".to_owned(),
+                    Poggers::from_index(pick).map(|vote| vote.to_string()).unwrap_or_default(),
+                    " in ".to_owned(),
+                    self.config.confirm_seconds.to_string(),
+                    "s, mods can !veto_league or !override_league <ability>".to_owned()
+                ].concat());
+            },
+            pick => {
+                record.winner = pick.and_then(Poggers::from_index).map(|vote| vote.to_string());
+                global_state.history.save(&record);
+                if let Some(pick) = pick {
+                    self.level_up(pick);
+                }
+            },
+        }
+        global_state.display.publish(self.state.to_view());
+    }
+    /** Stop counting without telling the results */
+    fn stop(&mut self, by: &str, global_state: &GlobalState) {
+        if self.state.poll.close(by) {
+            global_state.history.save(&self.state.to_record());
+        }
+        global_state.display.publish(self.state.to_view());
+        global_state.outbox.say("Stopped counting!".to_owned());
+    }
+    /**
+    Keep the ability that got through the confirmation window in the history
+    and level it, `veto` is what mods did about chat's pick if they did something.
+    */
+    fn confirm(&mut self, pick: usize, veto: Option<String>, global_state: &GlobalState) {
+        let mut record = self.state.to_record();
+        record.winner = Poggers::from_index(pick).map(|vote| vote.to_string());
+        record.veto = veto;
+        // Ties the streamer picked were saved without a winner when they closed
+        global_state.history.update(&record);
+        self.state.last_pick = Some(pick);
+        self.state.awaiting_pick = Vec::new();
+        global_state.display.publish(self.state.to_view());
+        self.level_up(pick);
+    }
+    /** Level the ability and check the level again once it's done */
+    fn level_up(&mut self, pick: usize) {
+        if let Some(vote) = Poggers::from_index(pick) {
+            log::info("[LeagueBot] Finished counting ability votes".to_owned());
+            tokio::spawn(LeagueBot::level_up_ability(vote));
+            self.state.force_check_level = true;
+        }
+    }
+    /** Run one of this bot's `COMMANDS`, the caller is allowed to run it */
+    async fn run_command(&mut self, name: &str, global_state: &GlobalState, msg: &PrivmsgMessage) {
+        match name {
+            "!results_league" if self.state.poll.is_counting => {
+                self.close_poll(&msg.sender.login, global_state);
+            }
+            "!results_league" => {
+                let message = self.state.get_results_message(Some(msg), None);
+                global_state.outbox.say(message);
+            }
+            "!pick_league" => {
+                let pick = command::args(msg).first().and_then(|arg| self.state.poll.option_named(arg));
+                match pick {
+                    _ if self.state.awaiting_pick.is_empty() => {
+                        global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " There's no tie to break".to_owned()].concat());
+                    },
+                    Some(pick) if self.state.awaiting_pick.contains(&pick) => {
+                        let name = Poggers::from_index(pick).map(|vote| vote.to_string()).unwrap_or_default();
+                        global_state.outbox.say(["Leveling ".to_owned(), name].concat());
+                        self.confirm(pick, None, global_state);
+                    },
+                    _ => {
+                        let names: Vec<String> = self.state.awaiting_pick.iter()
+                            .filter_map(|&i| Poggers::from_index(i))
+                            .map(|vote| vote.to_string())
+                            .collect();
+                        global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " Pick one of ".to_owned(), names.join(", ")].concat());
+                    },
+                }
+            }
+            "!reset_league" => {
+                self.start_poll(global_state).await;
+            }
+            "!stop_league" => {
+                self.stop(&msg.sender.login, global_state);
+            }
+            "!veto_league" | "!override_league" => {
+                let pick = match &self.state.poll.pending {
+                    Some(pending) => pending.pick,
+                    None => {
+                        global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " There's no ability waiting to be leveled".to_owned()].concat());
+                        return;
+                    },
+                };
+                let args = command::args(msg);
+                let replacement = if name == "!override_league" {
+                    match args.first().and_then(|arg| self.state.poll.option_named(arg)) {
+                        Some(option) => Some(option),
+                        None => {
+                            global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " Usage: !override_league <Q|W|E|R>".to_owned()].concat());
+                            return;
+                        },
+                    }
+                } else {
+                    match Veto::from_keyword(args.first().copied()) {
+                        Some(Veto::Rerun) => None,
+                        Some(Veto::RunnerUp) => match self.state.poll.runner_up(pick) {
+                            Some(runner_up) => Some(runner_up),
+                            None => {
+                                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " There's no runner-up".to_owned()].concat());
+                                return;
+                            },
+                        },
+                        None => {
+                            global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " Usage: !veto_league [rerun|runner_up]".to_owned()].concat());
+                            return;
+                        },
+                    }
+                };
+                self.state.poll.pending = None;
+                let vetoed = Poggers::from_index(pick).map(|vote| vote.to_string()).unwrap_or_default();
+                match replacement.and_then(|option| Poggers::from_index(option).map(|vote| (option, vote))) {
+                    Some((option, vote)) => {
+                        global_state.audit.record(self.name(), &msg.sender.login, [
+                            "overrode ".to_owned(), vetoed.clone(), " with ".to_owned(), vote.to_string()
+                        ].concat());
+                        global_state.outbox.say([vetoed.clone(), " got overridden, leveling ".to_owned(), vote.to_string()].concat());
+                        let veto = [vetoed, " replaced with ".to_owned(), vote.to_string(), " by ".to_owned(), msg.sender.login.clone()].concat();
+                        self.confirm(option, Some(veto), global_state);
+                    },
+                    None => {
+                        global_state.audit.record(self.name(), &msg.sender.login, ["vetoed ".to_owned(), vetoed.clone()].concat());
+                        // The vetoed vote stays in the history, without a winner
+                        let mut record = self.state.to_record();
+                        record.winner = None;
+                        record.veto = Some([vetoed.clone(), " vetoed by ".to_owned(), msg.sender.login.clone(), ", voted again".to_owned()].concat());
+                        global_state.history.save(&record);
+                        global_state.outbox.say([vetoed, " got vetoed, vote again!".to_owned()].concat());
+                        self.state.last_pick = None;
+                        self.state.should_poll_for_level = true;
+                    },
+                }
+            }
+            "!reconnect_league" => {
+                metrics::RECONNECTS.with_label_values(&["league"]).inc();
+                self.state.http_client_attempt_connect = true;
+                self.state.force_check_level = true;
+            }
+            _ => {}
+        }
+    }
+    /**
+    Apply the tie policy, returns the ability to level if there's one to level
+    right away.
+    */
+    fn break_tie(&mut self, tied: Vec<usize>, global_state: &GlobalState) -> Option<usize> {
+        let names: Vec<String> = tied.iter()
+            .filter_map(|&i| Poggers::from_index(i))
+            .map(|vote| vote.to_string())
+            .collect();
+        let tie = ["Tie between ".to_owned(), names.join(" and "), "!".to_owned()].concat();
+        // Runoffs that tie again would go on forever
+        let policy = match self.config.tie_policy {
+            TiePolicy::Runoff if !self.state.poll.restricted_to.is_empty() => TiePolicy::Random,
+            policy => policy,
+        };
+        match policy {
+            TiePolicy::Random => {
+                let pick = tally::pick_random(&tied);
+                let name = pick.and_then(Poggers::from_index).map(|vote| vote.to_string()).unwrap_or_default();
+                global_state.outbox.say([tie, " Picked ".to_owned(), name, " at random".to_owned()].concat());
+                pick
+            },
+            TiePolicy::Default => {
+                let pick = self.config.tie_default.checked_sub(1)
+                    .filter(|&i| i < 4)
+                    .or_else(|| tied.first().copied());
+                let name = pick.and_then(Poggers::from_index).map(|vote| vote.to_string()).unwrap_or_default();
+                global_state.outbox.say([tie, " Going with the default: ".to_owned(), name].concat());
+                pick
+            },
+            TiePolicy::StreamerDecides => {
+                global_state.outbox.say([tie, " The streamer decides with !pick_league ".to_owned(), names.join(" or ")].concat());
+                self.state.awaiting_pick = tied;
+                // Don't ask chat about this level again while the streamer picks
+                if let Some(lls) = &self.state.last_league_state {
+                    self.state.last_level = lls.level;
+                }
+                None
+            },
+            TiePolicy::Runoff => {
+                self.state.reset();
+                global_state.outbox.say([tie, " Vote again, only ".to_owned(), names.join(" or "), " count".to_owned()].concat());
+                self.state.poll.restricted_to = tied;
+                None
+            },
+        }
+    }
+    /** Returns false when a shared alias like `!veto` was used but this bot has nothing waiting on it */
+    fn is_waiting_for(&self, command: &Command, msg: &PrivmsgMessage) -> bool {
+        let by_name = msg.message_text.split_whitespace().next()
+            .is_some_and(|first| first.eq_ignore_ascii_case(command.name));
+        by_name || match command.name {
+            "!pick_league" => !self.state.awaiting_pick.is_empty(),
+            "!veto_league" | "!override_league" => self.state.poll.pending.is_some(),
+            _ => true,
+        }
+    }
     /** Attempt to press the Keyboard buttons to level up an ability */
     async fn level_up_ability(vote: Poggers) {
         // Press the upgrade buttons
@@ -337,12 +704,14 @@ impl LeagueBot {
         sleep(Duration::from_millis(20)).await; // This might become a problem
         ability_button.release();
         KeybdKey::LControlKey.release();
+        metrics::key_pressed("Ctrl");
+        metrics::key_pressed(&vote.to_string());
 
-        println!("{} {}", [
+        log::info(format!("{} {}", [
             "[LeagueBot]",
             " ",
             "Leveled up"
-            ].concat(), vote);
+            ].concat(), vote));
     }
     /** Attempt to press the KeySequence to initiate a ff vote */
     async fn try_to_ff() {
@@ -364,12 +733,20 @@ impl LeagueBot {
         sleep(Duration::from_millis(20)).await;
         KeybdKey::EnterKey.press();
         KeybdKey::EnterKey.release();
+        metrics::key_pressed("Enter");
+        metrics::key_pressed("Slash");
+        metrics::key_pressed("F");
+        metrics::key_pressed("F");
+        metrics::key_pressed("Enter");
     }
     /** Update the saved state of the league client */
     async fn update_league_client(&mut self) {
         // Run the casul GET request to the client backend
+        let started = std::time::Instant::now();
         match self.state.http_client.get(self.state.url.clone()).send().await {
             Ok(res) => {
+                metrics::LEAGUE_POLL_LATENCY.with_label_values(&["ok"])
+                    .observe(started.elapsed().as_secs_f64());
                 //println!("{:?}", res);
                 if res.status() == 200 {
                     let league_response: LeagueResponse = res.json().await.unwrap();
@@ -385,7 +762,10 @@ impl LeagueBot {
                 }
             },
             Err(err) => {
-                println!("{} Error connecting to the league client\n{}", "[LeagueBot]", err);
+                metrics::LEAGUE_POLL_LATENCY.with_label_values(&["error"])
+                    .observe(started.elapsed().as_secs_f64());
+                metrics::LEAGUE_POLL_FAILURES.inc();
+                log::error(format!("{} Error connecting to the league client\n{}", "[LeagueBot]", err));
                 self.state.http_client_attempt_connect = false;
                 self.state.http_client_connected = false;
             },
@@ -394,9 +774,10 @@ impl LeagueBot {
     /** Check if the level has changed since last checked */
     async fn check_league_client(&mut self) {
         if let Some(lls) = &self.state.last_league_state {
-            if !(&self.state.is_counting) && !(&self.state.should_poll_for_level) {
+            // A level waiting for confirmation isn't a new level
+            if !(&self.state.poll.is_counting) && !(&self.state.should_poll_for_level) && self.state.poll.pending.is_none() {
                 if lls.level > self.state.last_level.into() {
-                    println!("[LeagueBot] Level difference: {} -> {}", self.state.last_level, lls.level);
+                    log::info(format!("[LeagueBot] Level difference: {} -> {}", self.state.last_level, lls.level));
                     // Remember to poll for level
                     self.state.should_poll_for_level = true;
                 }
@@ -1,2 +1,32 @@
+use crate::util::command::Command;
+
+pub mod help_bot;
 pub mod league_bot;
-pub mod vote_bot;
\ No newline at end of file
+pub mod vote_bot;
+
+/** Every bot's commands, help is generated from this */
+pub const REGISTRY: &[(&str, &[Command])] = &[
+    ("HelpBot", help_bot::COMMANDS),
+    ("VoteBot", vote_bot::COMMANDS),
+    ("LeagueBot", league_bot::COMMANDS),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Twitch drops longer messages */
+    const MAX_MESSAGE_LENGTH: usize = 500;
+
+    #[test]
+    fn every_help_line_fits_in_a_chat_message() {
+        // `!help` mentions whoever asked, Twitch names are 25 characters at most
+        let mention = "@".len() + 25 + " ".len();
+        for (bot, commands) in REGISTRY {
+            for command in commands.iter() {
+                let length = mention + command.help_line().chars().count();
+                assert!(length <= MAX_MESSAGE_LENGTH, "{}'s {} help is {} characters", bot, command.name, length);
+            }
+        }
+    }
+}
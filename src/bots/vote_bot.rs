@@ -1,132 +1,2109 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
 use twitch_irc::message::TwitchUserBasics;
 use async_trait::async_trait;
-use crate::util::{bot::{Bot, GlobalState}, twitch::is_mod};
+use twitch_irc::message::PrivmsgMessage;
+use serde::{Deserialize, Serialize};
+use crate::util::{bot::{self, Bot, GlobalState, Handled}, command::{self, Command, Cooldowns, Permission, parse_duration}, metrics};
+use crate::util::tally::{self, format_number, Outcome, RatingStats, Runoff, TiePolicy};
+use crate::util::eligibility::{Eligibility, EligibilityRules};
+use crate::util::poll::{Ballot, Poll, PollOption, TallyStrategy, Veto};
+use crate::util::history::PollRecord;
+use crate::util::log;
+use crate::util::queue::QueuedPoll;
+use crate::util::normalize::{normalize_text, visible_text, vote_candidates, Strictness};
+use crate::util::twitch::{has_badge, is_mod, subscriber_tier};
+use crate::display::{OptionView, PollView, RatingView};
 
-pub struct Votes (i32, i32);
+const POLL_USAGE: &str = "!poll start [preset] or !poll reload or !poll history [count] or !poll queue ... or !poll [ranked|approval] [duration] [quorum=N|N%] [majority=N%] [strict|lenient] [secret] [only=subs,followers,chatters,returning] \"<question>\" | <option> | <option> ... or !poll rating [min-max] [duration] \"<question>\"";
+
+const QUEUE_USAGE: &str = "!poll queue [add <poll>|remove <n>|move <n> <m>|clear]";
+
+/** Suggestions end up as poll options, longer ones don't fit in chat */
+const MAX_SUGGESTION_LENGTH: usize = 50;
+
+const SUGGESTIONS_USAGE: &str = "!suggestions [open|close|poll [ranked|approval] [duration] [\"<question>\"]]";
+
+/** The `[vote_bot]` section of the config */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VoteBotConfig {
+    /** Remind chat once these fractions of a timed poll have passed */
+    #[serde(default = "default_reminder_fractions")]
+    pub reminder_fractions: Vec<f64>,
+    /** Remind chat when there's this many seconds left on a timed poll */
+    #[serde(default = "default_reminder_seconds_left")]
+    pub reminder_seconds_left: Vec<i64>,
+    /**
+    Tell chat how the poll is going every this many seconds, like `Mirage
+    leads 14–9 with 20s left`. Off if not set
+    */
+    #[serde(default)]
+    pub progress_interval: Option<i64>,
+    /** Last vote wins, viewers can change their vote or `!unvote` while the poll is open */
+    #[serde(default)]
+    pub allow_vote_change: bool,
+    #[serde(default)]
+    pub weights: VoteWeights,
+    /** Range of rating polls started without one */
+    #[serde(default = "default_rating_min")]
+    pub rating_min: f64,
+    #[serde(default = "default_rating_max")]
+    pub rating_max: f64,
+    /** What happens when the most voted options are tied */
+    #[serde(default)]
+    pub tie_policy: TiePolicy,
+    /** Number of the option the `default` tie policy goes with */
+    #[serde(default = "default_tie_default")]
+    pub tie_default: usize,
+    #[serde(default)]
+    pub quorum: QuorumConfig,
+    /** How much of a message has to look like a vote, polls can pick their own */
+    #[serde(default)]
+    pub strictness: Strictness,
+    /** Option text -> other words that vote for it, like `yes = ["sí", "sim"]` */
+    #[serde(default = "default_keywords")]
+    pub keywords: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub eligibility: EligibilityRules,
+    /** Every poll is a secret ballot, not only the ones started with `secret` */
+    #[serde(default)]
+    pub secret: bool,
+    /** Count down this many seconds in chat before a secret ballot is revealed, 5 at most */
+    #[serde(default)]
+    pub reveal_countdown: i64,
+    /** Seconds mods get to `!veto` or `!override` the winner of a poll, off if 0 */
+    #[serde(default)]
+    pub confirm_seconds: i64,
+    /** Seconds between a poll closing and the next queued one starting */
+    #[serde(default = "default_queue_gap")]
+    pub queue_gap: i64,
+    /** Polls queued by themselves every so often */
+    #[serde(default)]
+    pub schedule: Vec<ScheduledPoll>,
+    /** Name -> poll started with `!poll start <name>`, `!poll reload` reads them again */
+    #[serde(default)]
+    pub presets: Presets,
+}
+
+/**
+The config's presets. Clones share them so the admin API queues the same
+presets chat does, and `!poll reload` changes them for both.
+*/
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(from = "HashMap<String, PollPreset>", into = "HashMap<String, PollPreset>")]
+pub struct Presets {
+    presets: Arc<Mutex<HashMap<String, PollPreset>>>,
+}
+
+impl From<HashMap<String, PollPreset>> for Presets {
+    fn from(presets: HashMap<String, PollPreset>) -> Self {
+        Self { presets: Arc::new(Mutex::new(presets)) }
+    }
+}
+
+impl From<Presets> for HashMap<String, PollPreset> {
+    fn from(presets: Presets) -> Self {
+        presets.presets.lock().unwrap().clone()
+    }
+}
+
+impl Presets {
+    /** The preset with this name, whatever its case */
+    pub fn get(&self, name: &str) -> Option<(String, PollPreset)> {
+        self.presets.lock().unwrap().iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+            .map(|(name, preset)| (name.clone(), preset.clone()))
+    }
+    /** Every preset's name, sorted */
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.presets.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+    /** Swap every preset for the ones of a config that just got read, returns how many there are now */
+    pub fn replace(&self, presets: Presets) -> usize {
+        let presets: HashMap<String, PollPreset> = presets.into();
+        let mut current = self.presets.lock().unwrap();
+        *current = presets;
+        current.len()
+    }
+    /**
+    The poll a queued or scheduled text starts, it's either a preset's name or
+    what would follow `!poll`. Chat and the admin API both go through this.
+    */
+    pub fn setup_for(&self, text: &str) -> Option<PollSetup> {
+        match self.get(text) {
+            Some((name, preset)) => preset.setup(&name),
+            None => parse_poll_args(text),
+        }
+    }
+}
+
+/** A poll kept in the config to start in one go */
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PollPreset {
+    pub question: String,
+    pub options: Vec<String>,
+    pub mode: PollMode,
+    /** A duration like `60s`, the poll stays open until a mod closes it if not set */
+    pub duration: Option<String>,
+    /** Lowest and highest rating of rating polls */
+    pub range: Option<(f64, f64)>,
+    pub quorum: QuorumRules,
+    pub strictness: Option<Strictness>,
+    /** On top of the config's */
+    pub eligibility: EligibilityRules,
+    /** On top of the config's */
+    pub keywords: HashMap<String, Vec<String>>,
+    pub secret: bool,
+    /** Said in chat once the poll is decided, `{winner}` is replaced with the winner */
+    pub then_say: Option<String>,
+    /** Preset that gets to go next in the queue once the poll is decided */
+    pub then_start: Option<String>,
+}
+
+impl PollPreset {
+    /** The poll to start, `None` if the preset is missing its question, options or a valid duration */
+    pub fn setup(&self, name: &str) -> Option<PollSetup> {
+        let duration = match &self.duration {
+            Some(duration) => Some(parse_duration(duration)?),
+            None => None,
+        };
+        let is_rating = self.mode == PollMode::Rating;
+        if self.question.trim().is_empty() || (self.options.len() < 2 && !is_rating) {
+            return None;
+        }
+        Some(PollSetup {
+            mode: self.mode,
+            duration,
+            question: self.question.clone(),
+            labels: if is_rating { Vec::new() } else { self.options.clone() },
+            range: if is_rating { self.range } else { None },
+            quorum: self.quorum,
+            strictness: self.strictness,
+            eligibility: self.eligibility.clone(),
+            secret: self.secret,
+            keywords: self.keywords.clone(),
+            preset: Some(name.to_owned()),
+        })
+    }
+}
+
+/** A poll the config queues every so often, like `every = "30m"` */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledPoll {
+    /** A duration like `30m` or `1h30m`, the first one is queued after that long */
+    pub every: String,
+    /** What would follow `!poll` in chat */
+    pub poll: String,
+}
+
+/**
+Quorum and supermajority of a poll, `None` means there isn't one. Polls can
+set their own, anything they don't set comes from the config.
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct QuorumRules {
+    /** Fewest viewers that have to vote */
+    pub min_voters: Option<usize>,
+    /** Smallest share (0 to 1) of the active chatters that have to vote */
+    pub min_active_share: Option<f64>,
+    /** Smallest share (0 to 1) of the votes the winner needs */
+    pub supermajority: Option<f64>,
+}
+
+impl QuorumRules {
+    /** These rules, with the ones they don't set taken from `other` */
+    pub fn or(self, other: QuorumRules) -> QuorumRules {
+        QuorumRules {
+            min_voters: self.min_voters.or(other.min_voters),
+            min_active_share: self.min_active_share.or(other.min_active_share),
+            supermajority: self.supermajority.or(other.supermajority),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuorumFallback {
+    /** Give the poll more time, then go with the default */
+    Extend,
+    /** Go with the default option right away */
+    Default,
+}
+
+/** The `[vote_bot.quorum]` section of the config */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct QuorumConfig {
+    pub min_voters: Option<usize>,
+    pub min_active_share: Option<f64>,
+    pub supermajority: Option<f64>,
+    /** Chatters count as active for this many minutes after their last message */
+    pub active_minutes: i64,
+    pub fallback: QuorumFallback,
+    /** Seconds added each time a timed poll gets extended */
+    pub extend_by: i64,
+    pub max_extensions: u32,
+    /** Number of the option picked when the quorum isn't reached, 0 for none */
+    pub default_option: usize,
+}
+
+impl QuorumConfig {
+    pub fn rules(&self) -> QuorumRules {
+        QuorumRules {
+            min_voters: self.min_voters,
+            min_active_share: self.min_active_share,
+            supermajority: self.supermajority,
+        }
+    }
+}
+
+impl Default for QuorumConfig {
+    fn default() -> Self {
+        Self {
+            min_voters: None,
+            min_active_share: None,
+            supermajority: None,
+            active_minutes: 10,
+            fallback: QuorumFallback::Extend,
+            extend_by: 30,
+            max_extensions: 1,
+            default_option: 0,
+        }
+    }
+}
+
+/**
+The `[vote_bot.weights]` section of the config. A viewer's vote is worth the
+highest weight of the roles they have.
+*/
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct VoteWeights {
+    pub everyone: i32,
+    pub subscriber_tier1: i32,
+    pub subscriber_tier2: i32,
+    pub subscriber_tier3: i32,
+    pub vip: i32,
+    pub moderator: i32,
+    /** A cheer with a vote adds one vote per this many bits, 0 to ignore bits */
+    pub bits_per_vote: u64,
+}
+
+impl VoteWeights {
+    /** How much the sender's vote is worth */
+    pub fn weight_for(&self, msg: &PrivmsgMessage) -> i32 {
+        let mut weight = self.everyone;
+        match subscriber_tier(msg) {
+            Some(1) => weight = weight.max(self.subscriber_tier1),
+            Some(2) => weight = weight.max(self.subscriber_tier2),
+            Some(3) => weight = weight.max(self.subscriber_tier3),
+            _ => {}
+        }
+        if has_badge(msg, "vip") {
+            weight = weight.max(self.vip);
+        }
+        if is_mod(msg) {
+            weight = weight.max(self.moderator);
+        }
+        weight
+    }
+    /** Extra votes bought with the bits of a cheer */
+    pub fn bits_weight(&self, msg: &PrivmsgMessage) -> i32 {
+        match (msg.bits, self.bits_per_vote) {
+            (Some(bits), bits_per_vote) if bits_per_vote > 0 => (bits / bits_per_vote) as i32,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for VoteWeights {
+    fn default() -> Self {
+        Self {
+            everyone: 1,
+            subscriber_tier1: 1,
+            subscriber_tier2: 1,
+            subscriber_tier3: 1,
+            vip: 1,
+            moderator: 1,
+            bits_per_vote: 0,
+        }
+    }
+}
+
+fn default_reminder_fractions() -> Vec<f64> {
+    vec![0.5]
+}
+
+fn default_reminder_seconds_left() -> Vec<i64> {
+    vec![10]
+}
+
+fn default_rating_min() -> f64 {
+    1.0
+}
+
+fn default_rating_max() -> f64 {
+    10.0
+}
+
+fn default_queue_gap() -> i64 {
+    15
+}
+
+fn default_tie_default() -> usize {
+    1
+}
+
+fn default_keywords() -> HashMap<String, Vec<String>> {
+    let mut keywords = HashMap::new();
+    keywords.insert("yes".to_owned(), ["y", "yep", "yeah", "sí", "si", "sim", "oui", "ja"].iter().map(|k| k.to_string()).collect());
+    keywords.insert("no".to_owned(), ["n", "nope", "nah", "não", "nao", "non", "nein"].iter().map(|k| k.to_string()).collect());
+    keywords
+}
+
+impl Default for VoteBotConfig {
+    fn default() -> Self {
+        Self {
+            reminder_fractions: default_reminder_fractions(),
+            reminder_seconds_left: default_reminder_seconds_left(),
+            progress_interval: None,
+            allow_vote_change: false,
+            weights: Default::default(),
+            rating_min: default_rating_min(),
+            rating_max: default_rating_max(),
+            tie_policy: Default::default(),
+            tie_default: default_tie_default(),
+            quorum: Default::default(),
+            strictness: Default::default(),
+            keywords: default_keywords(),
+            eligibility: Default::default(),
+            secret: false,
+            reveal_countdown: 0,
+            confirm_seconds: 0,
+            queue_gap: default_queue_gap(),
+            schedule: Vec::new(),
+            presets: Presets::default(),
+        }
+    }
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "!poll",
+        aliases: &[],
+        usage: POLL_USAGE,
+        description: "Start a poll, chat votes with the option's number or its text. The README explains every mode, setting, preset and queue command.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!reset_votes",
+        aliases: &[],
+        usage: "!reset_votes [duration]",
+        description: "Start a Yes (1) or No (2) poll.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!extend",
+        aliases: &[],
+        usage: "!extend <duration>",
+        description: "Give the running poll more time, makes it timed if it wasn't.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!shorten",
+        aliases: &[],
+        usage: "!shorten <duration>",
+        description: "Take time away from the running timed poll.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!results",
+        aliases: &["!results_votes"],
+        usage: "!results [force]",
+        description: "Stop counting and tell chat the ranked results, force skips the quorum and supermajority.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!lastpoll",
+        aliases: &[],
+        usage: "!lastpoll",
+        description: "Tell chat the results of the last finished poll.",
+        permission: Permission::Everyone,
+        cooldown: Some(30),
+    },
+    Command {
+        name: "!suggest",
+        aliases: &[],
+        usage: "!suggest <idea>",
+        description: "Suggest an option for the next poll while suggestions are open, one per viewer.",
+        permission: Permission::Everyone,
+        cooldown: None,
+    },
+    Command {
+        name: "!suggestions",
+        aliases: &[],
+        usage: SUGGESTIONS_USAGE,
+        description: "List the suggestions, open or close them, or start a poll with the approved ones.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!approve",
+        aliases: &[],
+        usage: "!approve <number> ...",
+        description: "Approve suggestions so they're options of the next suggestions poll.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!reject",
+        aliases: &[],
+        usage: "!reject <number> ...",
+        description: "Reject suggestions, they won't make it into the poll.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!pick",
+        aliases: &[],
+        usage: "!pick <number>",
+        description: "Break a tie when the tie policy lets the streamer decide.",
+        permission: Permission::Broadcaster,
+        cooldown: None,
+    },
+    Command {
+        name: "!veto",
+        aliases: &[],
+        usage: "!veto [rerun|runner_up]",
+        description: "Veto the winner while it waits for confirmation, the poll runs again or the runner-up wins.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!override",
+        aliases: &[],
+        usage: "!override <option>",
+        description: "Replace the winner while it waits for confirmation, by the option's number or text.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+    Command {
+        name: "!unvote",
+        aliases: &[],
+        usage: "!unvote",
+        description: "Take back your vote, only when changing votes is allowed.",
+        permission: Permission::Everyone,
+        cooldown: None,
+    },
+    Command {
+        name: "!stop_votes",
+        aliases: &[],
+        usage: "!stop_votes",
+        description: "Stop counting without telling the results.",
+        permission: Permission::Moderator,
+        cooldown: None,
+    },
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PollMode {
+    /** One option per viewer, the most voted wins */
+    #[default]
+    Plurality,
+    /** Viewers rank the options, decided by instant runoff */
+    #[serde(alias = "ranked")]
+    RankedChoice,
+    /** Viewers pick every option they like, the most approved wins */
+    Approval,
+    /** Viewers give a number, no options */
+    Rating,
+}
+
+impl PollMode {
+    /** Mode for a keyword of the `!poll` command */
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_lowercase().as_str() {
+            "plurality" => Some(PollMode::Plurality),
+            "ranked" => Some(PollMode::RankedChoice),
+            "approval" => Some(PollMode::Approval),
+            "rating" => Some(PollMode::Rating),
+            _ => None,
+        }
+    }
+}
+
+impl TallyStrategy for PollMode {
+    /** Approval polls count every choice live, the others only the favourite */
+    fn counted<'a>(&self, choices: &'a [usize]) -> &'a [usize] {
+        match self {
+            PollMode::Approval => choices,
+            _ => &choices[..choices.len().min(1)],
+        }
+    }
+    fn outcome(&self, votes: &[i32], ballots: &[&Ballot]) -> Outcome {
+        if *self != PollMode::RankedChoice {
+            return tally::outcome(votes);
+        }
+        let runoff = instant_runoff(votes.len(), ballots);
+        match (runoff.winner, runoff.rounds.last()) {
+            (Some(winner), _) => Outcome::Winner(winner),
+            // The last round is either empty or has everyone left tied
+            (None, Some(last)) => tally::outcome(&last.tallies),
+            (None, None) => Outcome::NoVotes,
+        }
+    }
+}
+
+/** Instant runoff over ranked ballots */
+fn instant_runoff(option_count: usize, ballots: &[&Ballot]) -> Runoff {
+    let ballots: Vec<(&[usize], i32)> = ballots.iter()
+        .map(|ballot| (ballot.choices.as_slice(), ballot.amount))
+        .collect();
+    tally::instant_runoff(option_count, &ballots)
+}
+
+/** Everything needed to start a poll */
+#[derive(Default)]
+pub struct PollSetup {
+    pub mode: PollMode,
+    /** In seconds, `None` for polls waiting for a mod to close them */
+    pub duration: Option<i64>,
+    pub question: String,
+    pub labels: Vec<String>,
+    /** Lowest and highest rating of rating polls, the config's if not given */
+    pub range: Option<(f64, f64)>,
+    pub quorum: QuorumRules,
+    /** The config's if not given */
+    pub strictness: Option<Strictness>,
+    /** On top of the config's */
+    pub eligibility: EligibilityRules,
+    /** Nobody sees the tally before the poll closes */
+    pub secret: bool,
+    /** Option text -> other words that vote for it, on top of the config's */
+    pub keywords: HashMap<String, Vec<String>>,
+    /** Name of the preset the poll comes from, for its follow-ups */
+    pub preset: Option<String>,
+}
+
+impl PollSetup {
+    /** The good old yes or no poll */
+    pub fn yes_no(duration: Option<i64>) -> Self {
+        Self {
+            duration,
+            question: "Yes or No?".to_owned(),
+            labels: vec!["Yes".to_owned(), "No".to_owned()],
+            ..Default::default()
+        }
+    }
+}
 
 pub struct State {
-    pub is_counting: bool,
-    pub voting_box: Votes,
-    pub who_voted: Vec<String>,
-    pub reset_timestamp: i64,
+    pub poll: Poll<String>,
+    pub mode: PollMode,
+    pub question: String,
+    /** Other words voting for each option, from the config's keywords */
+    pub keywords: Vec<Vec<String>>,
+    pub strictness: Strictness,
+    /** Lowest and highest rating accepted by rating polls */
+    pub rating_range: (f64, f64),
+    /** Tied options waiting for the streamer to `!pick` one */
+    pub awaiting_pick: Vec<usize>,
+    /** This poll is the re-vote of a tie */
+    pub is_runoff: bool,
+    pub quorum: QuorumRules,
+    /** How many times the poll got extended for missing its quorum */
+    pub extensions: u32,
+    /** The poll closed without reaching its quorum */
+    pub failed_quorum: bool,
+    /** When to tell chat how the poll is going next */
+    pub progress_at: i64,
+    /** What chat was last told about how the poll is going */
+    pub last_progress: String,
+    /** The tally stays hidden until the poll closes */
+    pub is_secret: bool,
+    /** Last number of the reveal countdown said in chat, 0 before it starts */
+    pub countdown_said: i64,
+    /**
+    Who asked for the results of a secret ballot, it closes once the countdown
+    is over. True if they forced them past the quorum
+    */
+    pub revealed_by: Option<(String, bool)>,
+    /** Name of the preset the poll comes from */
+    pub preset: Option<String>,
+    /** Bare numbers vote in this poll, they go to another bot's poll otherwise */
+    pub owns_numbers: bool,
     pub bot_is_enabled: bool,
 }
 
 impl State {
+    /**
+    Ranked tally, like `Which map? 1. Mirage 14, 2. Dust 9`. The amount of
+    voters is added when it's not the same as the weighted total, approval
+    polls also show the share of voters approving each option.
+    */
     pub fn to_string(&self) -> String {
-        let strings = [
-            self.voting_box.0.to_string(), 
-            " voted yes, ".to_owned(), 
-            self.voting_box.1.to_string(), 
-            " voted no!".to_owned()
-        ];
+        let rejections = self.poll.rejections.summary()
+            .map(|summary| [" (".to_owned(), summary, ")".to_owned()].concat())
+            .unwrap_or_default();
+        if self.mode == PollMode::Rating {
+            return [self.question.clone(), " ".to_owned(), self.rating_summary(), rejections].concat();
+        }
+        let voter_count = self.poll.voters.len() as i32;
+        let tally: Vec<String> = self.poll.ranked().iter().enumerate()
+            .map(|(i, option)| {
+                let mut strings = vec![
+                    (i + 1).to_string(),
+                    ". ".to_owned(),
+                    option.label(),
+                    " ".to_owned(),
+                    option.votes.to_string()
+                ];
+                if option.voters != option.votes {
+                    strings.push([" (".to_owned(), option.voters.to_string(), " voters)".to_owned()].concat());
+                }
+                if self.mode == PollMode::Approval && voter_count > 0 {
+                    let share = option.voters * 100 / voter_count;
+                    strings.push([" ".to_owned(), share.to_string(), "% approve".to_owned()].concat());
+                }
+                strings.concat()
+            })
+            .collect();
+        let mut strings = vec![self.question.clone(), " ".to_owned(), tally.join(", "), rejections];
+        // The runoff is only worth explaining once it's over
+        if let (Some(runoff), false) = (self.runoff(), self.poll.is_counting) {
+            strings.push(". ".to_owned());
+            strings.push(self.runoff_summary(&runoff));
+        }
         strings.concat()
     }
-
-    pub fn reset(&mut self) {
-        self.is_counting = true;
-        self.voting_box = Votes(0,0);
-        self.who_voted = Vec::new();
-        self.reset_timestamp = chrono::offset::Local::now().timestamp_millis();
+    /** Set up a new poll and start counting, timed polls close by themselves */
+    pub fn start(&mut self, setup: PollSetup, config: &VoteBotConfig, by: &str) {
+        self.mode = setup.mode;
+        self.is_runoff = false;
+        self.quorum = setup.quorum.or(config.quorum.rules());
+        self.strictness = setup.strictness.unwrap_or(config.strictness);
+        self.poll.eligibility = config.eligibility.and(&setup.eligibility);
+        self.keywords = setup.labels.iter()
+            .map(|label| {
+                let label = normalize_text(label);
+                let mut keywords: Vec<String> = config.keywords.iter().chain(setup.keywords.iter())
+                    .filter(|(option, _)| normalize_text(option) == label)
+                    .flat_map(|(_, keywords)| keywords.iter().cloned())
+                    .collect();
+                keywords.sort();
+                keywords.dedup();
+                keywords
+            })
+            .collect();
+        self.rating_range = setup.range.unwrap_or((config.rating_min, config.rating_max));
+        self.question = setup.question;
+        self.awaiting_pick = Vec::new();
+        self.extensions = 0;
+        self.failed_quorum = false;
+        self.last_progress = String::new();
+        self.is_secret = setup.secret || config.secret;
+        self.countdown_said = 0;
+        self.revealed_by = None;
+        self.preset = setup.preset;
+        self.poll.strategy = Box::new(setup.mode);
+        self.poll.secret = self.is_secret;
+        self.poll.open(setup.labels, by);
+        self.progress_at = self.poll.opened_at + config.progress_interval.unwrap_or(0) * 1000;
+        let closes_at = setup.duration.map(|duration| self.poll.opened_at + duration * 1000);
+        self.set_closes_at(closes_at, config);
     }
-
-    pub fn stop_counting(&mut self) {
-        self.is_counting = false;
+    /** Change when the poll closes and plan the reminders that are still ahead */
+    pub fn set_closes_at(&mut self, closes_at: Option<i64>, config: &VoteBotConfig) {
+        self.poll.set_closes_at(closes_at, &config.reminder_fractions, &config.reminder_seconds_left);
     }
-
-    pub fn add_vote(&mut self, amount: i32, to: i8, voter: &TwitchUserBasics) {
-        match to {
-            0 => {
-                self.voting_box.0 = self.voting_box.0 + amount;
+    /**
+    Find which options a chat message votes for, either by their number
+    (starting at 1) or by the text of one option. Ranked polls take several
+    numbers in order, like `3 1 2` or `3>1>2`, approval polls several numbers
+    in any order.
+    */
+    pub fn parse_ballot(&self, text: &str) -> Option<Vec<usize>> {
+        let text = text.trim();
+        if let Some(index) = self.option_named(text) {
+            return Some(vec![index]);
+        }
+        if !self.owns_numbers {
+            return None;
+        }
+        let numbers: Vec<&str> = text.split(|c: char| c.is_whitespace() || c == ',' || c == '>')
+            .filter(|part| !part.is_empty())
+            .collect();
+        if numbers.is_empty() || (numbers.len() > 1 && self.mode == PollMode::Plurality) {
+            return None;
+        }
+        let mut choices = Vec::new();
+        for number in numbers {
+            let number = number.parse::<usize>().ok()?;
+            if number < 1 || number > self.poll.options.len() || choices.contains(&(number - 1)) {
+                return None;
+            }
+            choices.push(number - 1);
+        }
+        // So "3 1" and "1 3" are the same ballot
+        if self.mode == PollMode::Approval {
+            choices.sort_unstable();
+        }
+        Some(choices)
+    }
+    /** The option called `text`, by its text or one of its keywords */
+    fn option_named(&self, text: &str) -> Option<usize> {
+        let normalized = normalize_text(text);
+        let matches = |name: &String| match self.strictness {
+            Strictness::Strict => name.to_lowercase() == text.to_lowercase(),
+            _ => !normalized.is_empty() && normalize_text(name) == normalized,
+        };
+        (0..self.poll.options.len()).find(|&i| {
+            matches(&self.poll.options[i].value) || self.keywords.get(i).into_iter().flatten().any(matches)
+        })
+    }
+    /** The rating a chat message gives, only on rating polls and within the range */
+    pub fn parse_rating(&self, text: &str) -> Option<f64> {
+        if self.mode != PollMode::Rating || !self.owns_numbers {
+            return None;
+        }
+        // Some people write 7,5
+        let rating = text.trim().replace(',', ".").parse::<f64>().ok()?;
+        let (min, max) = self.rating_range;
+        if rating.is_finite() && rating >= min && rating <= max {
+            Some(rating)
+        } else {
+            None
+        }
+    }
+    /** How to vote, like `Vote 1 for Dust, 2 for Mirage!` */
+    pub fn vote_instructions(&self) -> String {
+        if !self.owns_numbers {
+            let labels: Vec<String> = self.poll.options.iter().map(|option| option.label()).collect();
+            return match self.mode {
+                PollMode::Rating => "Numbers go to another poll right now, rate it once it closes!".to_owned(),
+                _ => ["Numbers go to another poll right now, type ".to_owned(), labels.join(", "), " to vote!".to_owned()].concat(),
+            };
+        }
+        let options: Vec<String> = self.poll.options.iter().enumerate()
+            .map(|(i, option)| [(i + 1).to_string(), " for ".to_owned(), option.label()].concat())
+            .collect();
+        match self.mode {
+            PollMode::Plurality => ["Vote ".to_owned(), options.join(", "), "!".to_owned()].concat(),
+            PollMode::RankedChoice => [
+                "Rank them from best to worst like \"3 1 2\", ".to_owned(),
+                options.join(", "),
+                "!".to_owned()
+            ].concat(),
+            PollMode::Approval => [
+                "Pick every option you like like \"1 3 4\", ".to_owned(),
+                options.join(", "),
+                "!".to_owned()
+            ].concat(),
+            PollMode::Rating => [
+                "Rate it from ".to_owned(),
+                format_number(self.rating_range.0),
+                " to ".to_owned(),
+                format_number(self.rating_range.1),
+                "!".to_owned()
+            ].concat(),
+        }
+    }
+    /** Instant runoff over the ballots, only for ranked polls */
+    pub fn runoff(&self) -> Option<Runoff> {
+        if self.mode != PollMode::RankedChoice {
+            return None;
+        }
+        let ballots: Vec<&Ballot> = self.poll.voters.values().collect();
+        Some(instant_runoff(self.poll.options.len(), &ballots))
+    }
+    /** Round by round eliminations, like `Round 1: Dust out with 3. Mirage wins with 12 of 20` */
+    pub fn runoff_summary(&self, runoff: &Runoff) -> String {
+        let mut strings: Vec<String> = runoff.rounds.iter().enumerate()
+            .filter_map(|(i, round)| {
+                let eliminated = round.eliminated?;
+                Some([
+                    "Round ".to_owned(),
+                    (i + 1).to_string(),
+                    ": ".to_owned(),
+                    self.poll.options[eliminated].label(),
+                    " out with ".to_owned(),
+                    round.tallies[eliminated].to_string()
+                ].concat())
+            })
+            .collect();
+        if let (Some(winner), Some(last)) = (runoff.winner, runoff.rounds.last()) {
+            strings.push([
+                self.poll.options[winner].label(),
+                " wins with ".to_owned(),
+                last.tallies[winner].to_string(),
+                " of ".to_owned(),
+                last.tallies.iter().sum::<i32>().to_string()
+            ].concat());
+        }
+        strings.join(". ")
+    }
+    /** Statistics of the ratings, only for rating polls */
+    pub fn rating_stats(&self) -> Option<RatingStats> {
+        if self.mode != PollMode::Rating {
+            return None;
+        }
+        let ratings: Vec<f64> = self.poll.voters.values().filter_map(|ballot| ballot.rating).collect();
+        Some(tally::rating_stats(&ratings, self.rating_range.0, self.rating_range.1))
+    }
+    /** Like `12 ratings, mean 7.4, median 8. 6:## 7:#### 8:######` */
+    pub fn rating_summary(&self) -> String {
+        let stats = match self.rating_stats() {
+            Some(stats) if stats.count > 0 => stats,
+            _ => return "No ratings yet".to_owned(),
+        };
+        [
+            stats.count.to_string(),
+            if stats.count == 1 { " rating".to_owned() } else { " ratings".to_owned() },
+            ", mean ".to_owned(),
+            stats.mean.map(format_number).unwrap_or_default(),
+            ", median ".to_owned(),
+            stats.median.map(format_number).unwrap_or_default(),
+            ". ".to_owned(),
+            tally::ascii_histogram(&stats.buckets)
+        ].concat()
+    }
+    /**
+    How the poll is going, like `Mirage leads 14–9` or `Dust and Mirage are
+    tied at 5`. `None` until someone votes.
+    */
+    pub fn progress(&self) -> Option<String> {
+        if self.poll.voters.is_empty() {
+            return None;
+        }
+        if let Some(stats) = self.rating_stats() {
+            return Some([
+                stats.count.to_string(),
+                if stats.count == 1 { " rating".to_owned() } else { " ratings".to_owned() },
+                " so far, mean ".to_owned(),
+                stats.mean.map(format_number).unwrap_or_default()
+            ].concat());
+        }
+        let ranked = self.poll.ranked();
+        let top = ranked.first()?.votes;
+        let leaders: Vec<String> = ranked.iter()
+            .take_while(|option| option.votes == top)
+            .map(|option| option.label())
+            .collect();
+        if leaders.len() > 1 {
+            return Some([leaders.join(" and "), " are tied at ".to_owned(), top.to_string()].concat());
+        }
+        // Ranked polls only tally the favourites live, the runoff could still go another way
+        let leads = if self.mode == PollMode::RankedChoice { " leads the first choices " } else { " leads " };
+        let mut strings = vec![leaders[0].clone(), leads.to_owned(), top.to_string()];
+        if let Some(second) = ranked.get(1) {
+            strings.push(["–".to_owned(), second.votes.to_string()].concat());
+        }
+        Some(strings.concat())
+    }
+    /** The winning option, `None` if nobody voted, for unbroken ties and for rating polls */
+    pub fn winner(&self) -> Option<&PollOption<String>> {
+        self.winner_index().and_then(|winner| self.poll.options.get(winner))
+    }
+    /** Index of the winning option, like `winner` */
+    pub fn winner_index(&self) -> Option<usize> {
+        if self.mode == PollMode::Rating || (self.failed_quorum && self.poll.decided.is_none()) {
+            return None;
+        }
+        self.poll.winner_index()
+    }
+    /** The poll for the history */
+    pub fn to_record(&self) -> PollRecord {
+        PollRecord::new(&self.poll, &self.question, self.winner().map(|option| option.label()))
+    }
+    /** The same poll over again */
+    pub fn rerun_setup(&self) -> PollSetup {
+        let labels: Vec<String> = self.poll.options.iter().map(|option| option.label()).collect();
+        PollSetup {
+            mode: self.mode,
+            duration: self.poll.closes_at.map(|closes_at| (closes_at - self.poll.opened_at) / 1000),
+            question: self.question.clone(),
+            keywords: labels.iter().cloned().zip(self.keywords.iter().cloned()).collect(),
+            labels,
+            range: Some(self.rating_range),
+            quorum: self.quorum,
+            strictness: Some(self.strictness),
+            eligibility: self.poll.eligibility.clone(),
+            secret: self.is_secret,
+            preset: self.preset.clone(),
+        }
+    }
+    /**
+    Why the poll doesn't count, `None` if it reached its quorum and the winner
+    has the supermajority. Ties are left to the tie policy.
+    */
+    pub fn quorum_failure(&self, active_chatters: usize) -> Option<String> {
+        let voter_count = self.poll.voters.len();
+        if let Some(min_voters) = self.quorum.min_voters {
+            if voter_count < min_voters {
+                return Some([
+                    "Only ".to_owned(), voter_count.to_string(), " of the ".to_owned(),
+                    min_voters.to_string(), " voters needed".to_owned()
+                ].concat());
             }
-            1 => {
-                self.voting_box.1 = self.voting_box.1 + amount;
+        }
+        if let Some(min_share) = self.quorum.min_active_share {
+            // Voters are chatters too, even the ones that only voted
+            let active_chatters = active_chatters.max(voter_count).max(1);
+            let share = voter_count as f64 / active_chatters as f64;
+            if share < min_share {
+                return Some([
+                    "Only ".to_owned(), percent(share), " of active chatters voted, ".to_owned(),
+                    percent(min_share), " needed".to_owned()
+                ].concat());
+            }
+        }
+        if let (Some(supermajority), Outcome::Winner(winner)) = (self.quorum.supermajority, self.poll.outcome()) {
+            let share = match (self.mode, self.runoff().and_then(|runoff| runoff.rounds.last().map(|round| round.tallies.clone()))) {
+                (PollMode::Rating, _) => return None,
+                (PollMode::Approval, _) => self.poll.options[winner].voters as f64 / voter_count.max(1) as f64,
+                (PollMode::RankedChoice, Some(tallies)) => {
+                    tallies[winner] as f64 / tallies.iter().sum::<i32>().max(1) as f64
+                },
+                _ => {
+                    let total: i32 = self.poll.options.iter().map(|option| option.votes).sum();
+                    self.poll.options[winner].votes as f64 / total.max(1) as f64
+                },
+            };
+            if share < supermajority {
+                return Some([
+                    self.poll.options[winner].label(), " only got ".to_owned(), percent(share),
+                    " of the votes, ".to_owned(), percent(supermajority), " needed".to_owned()
+                ].concat());
             }
-            _ => {}
         }
-        self.who_voted.push(voter.id.clone())
+        None
     }
-
-    pub fn can_vote(&self, voter: &TwitchUserBasics) -> bool {
-        self.is_counting && !self.who_voted.contains(&voter.id)
+    /** Snapshot of the voting box for the displays */
+    pub fn to_view(&self) -> PollView {
+        if self.is_secret && self.poll.is_counting {
+            return PollView {
+                bot: "VoteBot".to_owned(),
+                question: self.question.clone(),
+                options: self.poll.options.iter()
+                    .map(|option| OptionView { label: option.label(), votes: 0, voters: 0 })
+                    .collect(),
+                is_open: true,
+                closes_at: self.poll.closes_at,
+                winner: None,
+                rating: None,
+                secret_voters: Some(self.poll.voters.len()),
+            };
+        }
+        if let Some(stats) = self.rating_stats() {
+            return PollView {
+                bot: "VoteBot".to_owned(),
+                question: self.question.clone(),
+                options: stats.buckets.iter()
+                    .map(|bucket| OptionView { label: bucket.label.clone(), votes: bucket.count, voters: bucket.count })
+                    .collect(),
+                is_open: self.poll.is_counting,
+                closes_at: if self.poll.is_counting { self.poll.closes_at } else { None },
+                winner: None,
+                rating: Some(RatingView { count: stats.count, mean: stats.mean, median: stats.median }),
+                secret_voters: None,
+            };
+        }
+        let winner = if self.poll.is_counting {
+            None
+        } else {
+            Some(match (self.winner(), self.poll.outcome()) {
+                (Some(option), _) => option.label(),
+                (None, _) if self.failed_quorum => "No quorum".to_owned(),
+                (None, Outcome::NoVotes) => "No votes".to_owned(),
+                (None, _) => "Tie".to_owned(),
+            })
+        };
+        PollView {
+            bot: "VoteBot".to_owned(),
+            question: self.question.clone(),
+            options: self.poll.option_views(),
+            is_open: self.poll.is_counting,
+            closes_at: if self.poll.is_counting { self.poll.closes_at } else { None },
+            winner,
+            rating: None,
+            secret_voters: None,
+        }
     }
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
-            is_counting: false,
-            voting_box: Votes(0,0),
-            who_voted: Vec::new(),
-            reset_timestamp: chrono::offset::Local::now().timestamp_millis(),
+            poll: Poll::new("VoteBot"),
+            mode: PollMode::Plurality,
+            question: String::new(),
+            keywords: Vec::new(),
+            strictness: Default::default(),
+            rating_range: (1.0, 10.0),
+            awaiting_pick: Vec::new(),
+            is_runoff: false,
+            quorum: Default::default(),
+            extensions: 0,
+            failed_quorum: false,
+            progress_at: 0,
+            last_progress: String::new(),
+            is_secret: false,
+            countdown_said: 0,
+            revealed_by: None,
+            preset: None,
+            owns_numbers: true,
             bot_is_enabled: true,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum SuggestionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+pub struct Suggestion {
+    pub text: String,
+    /** Id of the viewer who suggested it */
+    pub author: String,
+    pub status: SuggestionStatus,
+}
+
+/** Ideas from chat waiting for mods to pick the ones that go in a poll */
+#[derive(Default)]
+pub struct Suggestions {
+    pub is_open: bool,
+    pub list: Vec<Suggestion>,
+}
+
+impl Suggestions {
+    /** Start collecting from scratch */
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.list = Vec::new();
+    }
+    /**
+    Add a viewer's suggestion, returns its number or the error to tell them.
+    Viewers get one suggestion each and the same idea can't be in twice, even
+    with other caps or punctuation.
+    */
+    pub fn add(&mut self, text: &str, author: &TwitchUserBasics) -> Result<usize, String> {
+        // `|` separates poll options
+        let text: String = text.chars().filter(|&c| !c.is_control() && c != '|').collect();
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if !self.is_open {
+            return Err("Suggestions are closed".to_owned());
+        }
+        let normalized = normalize_text(&text);
+        if normalized.is_empty() {
+            return Err("Usage: !suggest <idea>".to_owned());
+        }
+        if text.chars().count() > MAX_SUGGESTION_LENGTH {
+            return Err(["Keep it under ".to_owned(), MAX_SUGGESTION_LENGTH.to_string(), " characters".to_owned()].concat());
+        }
+        if self.list.iter().any(|suggestion| suggestion.author == author.id) {
+            return Err("You already suggested something".to_owned());
+        }
+        let duplicate = self.list.iter().position(|suggestion| normalize_text(&suggestion.text) == normalized);
+        if let Some(i) = duplicate {
+            return Err(["That's already suggestion #".to_owned(), (i + 1).to_string()].concat());
+        }
+        self.list.push(Suggestion { text, author: author.id.clone(), status: SuggestionStatus::Pending });
+        Ok(self.list.len())
+    }
+    /** Change the status of suggestions by their number, returns how many changed */
+    pub fn set_status(&mut self, numbers: &[usize], status: SuggestionStatus) -> usize {
+        let mut changed = 0;
+        for &number in numbers {
+            if let Some(suggestion) = number.checked_sub(1).and_then(|i| self.list.get_mut(i)) {
+                suggestion.status = status;
+                changed += 1;
+            }
+        }
+        changed
+    }
+    /** Text of the approved suggestions, in the order they were suggested */
+    pub fn approved(&self) -> Vec<String> {
+        self.list.iter()
+            .filter(|suggestion| suggestion.status == SuggestionStatus::Approved)
+            .map(|suggestion| suggestion.text.clone())
+            .collect()
+    }
+}
+
+/** Like `1. Dust, 2. Mirage (approved), 3. Nuke (rejected)` */
+impl Display for Suggestions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.list.is_empty() {
+            return write!(f, "No suggestions yet");
+        }
+        let suggestions: Vec<String> = self.list.iter().enumerate()
+            .map(|(i, suggestion)| {
+                let status = match suggestion.status {
+                    SuggestionStatus::Pending => "",
+                    SuggestionStatus::Approved => " (approved)",
+                    SuggestionStatus::Rejected => " (rejected)",
+                };
+                [(i + 1).to_string(), ". ".to_owned(), suggestion.text.clone(), status.to_owned()].concat()
+            })
+            .collect();
+        write!(f, "{}", suggestions.join(", "))
+    }
+}
+
 pub struct VoteBot {
     pub state: State,
+    pub suggestions: Suggestions,
+    /** Chatter id -> when they last said anything, for quorums based on active chatters */
+    pub last_seen: HashMap<String, i64>,
+    pub chatters: Eligibility,
+    pub cooldowns: Cooldowns,
+    pub config: VoteBotConfig,
+    /** The config's schedule with when each poll gets queued next */
+    pub schedule: Vec<Schedule>,
+}
+
+/** A scheduled poll that parsed fine */
+pub struct Schedule {
+    pub every: i64,
+    pub poll: String,
+    pub question: String,
+    pub next_at: i64,
 }
 
 impl Default for VoteBot {
     fn default() -> Self {
-        Self { state: Default::default() }
+        Self {
+            state: Default::default(),
+            suggestions: Default::default(),
+            last_seen: HashMap::new(),
+            chatters: Default::default(),
+            cooldowns: Default::default(),
+            config: Default::default(),
+            schedule: Vec::new(),
+        }
+    }
+}
+
+impl VoteBot {
+    pub fn new(config: VoteBotConfig) -> Self {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        let schedule = config.schedule.iter()
+            .filter_map(|scheduled| {
+                let every = parse_duration(&scheduled.every);
+                let setup = config.presets.setup_for(&scheduled.poll);
+                match (every, setup) {
+                    (Some(every), Some(setup)) => Some(Schedule {
+                        every,
+                        poll: scheduled.poll.clone(),
+                        question: setup.question,
+                        next_at: now + every * 1000,
+                    }),
+                    _ => {
+                        log::error(format!("[VoteBot] Skipped the scheduled poll {:?}, couldn't parse it", scheduled.poll));
+                        None
+                    },
+                }
+            })
+            .collect();
+        Self { config, schedule, ..Default::default() }
     }
 }
 
 #[async_trait]
 impl Bot for VoteBot {
+    fn name(&self) -> &str {
+        "VoteBot"
+    }
+
+    fn priority(&self) -> i32 {
+        10
+    }
+
     fn is_enabled(&mut self) -> bool {
         self.state.bot_is_enabled
     }
 
-    async fn handle_message(&mut self, global_state: &GlobalState, client: &twitch_irc::TwitchIRCClient<twitch_irc::SecureTCPTransport, twitch_irc::login::StaticLoginCredentials>, msg: &twitch_irc::message::PrivmsgMessage) {
-        match msg.message_text.to_uppercase().as_str() {
-            "1" | "YES" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 0, &msg.sender);
+    fn set_enabled(&mut self, enabled: bool) {
+        self.state.bot_is_enabled = enabled;
+    }
+
+    fn observe(&mut self, msg: &PrivmsgMessage) {
+        self.last_seen.insert(msg.sender.id.clone(), chrono::offset::Local::now().timestamp_millis());
+        self.chatters.saw(msg);
+    }
+
+    async fn handle_message(&mut self, global_state: &GlobalState, msg: &PrivmsgMessage) -> Handled {
+        if let Some(command) = command::find(COMMANDS, msg) {
+            if self.cooldowns.ready(command) {
+                metrics::command_handled(self.name(), command.name);
+                self.run_command(command.name, global_state, msg).await;
+            }
+            return Handled::Consumed;
+        }
+        self.state.owns_numbers = global_state.numbers.sync(self.name(), self.state.poll.is_counting);
+        let candidates = vote_candidates(msg, self.state.strictness);
+        let ballot = candidates.iter().find_map(|text| self.state.parse_ballot(text));
+        let rating = candidates.iter().find_map(|text| self.state.parse_rating(text));
+        if !self.state.poll.is_counting || (ballot.is_none() && rating.is_none()) {
+            return Handled::Passed;
+        }
+        // It's a vote on this poll from here on, even if it doesn't count
+        if !self.state.poll.check_eligibility(&self.chatters, msg, &global_state.helix) {
+            return Handled::Consumed;
+        }
+        if let Some(choices) = ballot {
+            let already_there = self.state.poll.voters.get(&msg.sender.id)
+                .map_or(false, |ballot| ballot.choices == choices);
+            if self.state.poll.can_vote(&msg.sender, &choices, self.config.allow_vote_change) && !already_there {
+                // Bits are part of the ballot so they go away with it, they only count on plurality polls
+                let mut amount = self.config.weights.weight_for(msg);
+                if self.state.mode == PollMode::Plurality {
+                    amount += self.config.weights.bits_weight(msg);
+                }
+                self.state.poll.add_vote(amount, choices, &msg.sender);
+            }
+            global_state.display.publish(self.state.to_view());
+        } else if let Some(rating) = rating {
+            let already_there = self.state.poll.voters.get(&msg.sender.id)
+                .map_or(false, |ballot| ballot.rating == Some(rating));
+            if self.state.poll.can_vote(&msg.sender, &[], self.config.allow_vote_change) && !already_there {
+                self.state.poll.add_rating(rating, &msg.sender);
+                global_state.display.publish(self.state.to_view());
+            }
+        }
+        Handled::Consumed
+    }
+
+    async fn update(&mut self, global_state: &GlobalState) {
+        //println!("Vote Bot Updated");
+        let now = chrono::offset::Local::now().timestamp_millis();
+        // Votes that waited on Twitch go through the same checks again
+        for msg in std::mem::take(&mut self.state.poll.waiting) {
+            self.handle_message(global_state, &msg).await;
+        }
+        if self.state.poll.take_confirmed(now).is_some() {
+            self.save_history(global_state);
+            self.follow_up(global_state);
+        }
+        self.queue_scheduled(now, global_state);
+        self.state.owns_numbers = global_state.numbers.sync(self.name(), self.state.poll.is_counting);
+        if !self.state.poll.is_counting {
+            self.start_queued(now, global_state);
+            return;
+        }
+        // Close timed polls
+        if self.state.poll.is_due(now) {
+            self.close_poll("timer", false, global_state);
+            return;
+        }
+        if self.state.poll.take_due_reminder(now) {
+            let seconds_left = self.state.poll.seconds_left().unwrap_or(0);
+            global_state.outbox.say([
+                seconds_left.to_string(),
+                "s left! ".to_owned(),
+                self.state.question.clone(),
+                " ".to_owned(),
+                self.state.vote_instructions()
+            ].concat());
+            return;
+        }
+        if self.state.is_secret {
+            self.count_down(global_state);
+            return;
+        }
+        if let Some(interval) = self.config.progress_interval {
+            if now >= self.state.progress_at {
+                self.state.progress_at = now + interval.max(1) * 1000;
+                self.announce_progress(global_state);
+            }
+        }
+    }
+
+    async fn start_poll(&mut self, global_state: &GlobalState) {
+        self.open_poll(PollSetup::yes_no(None), "dashboard", global_state);
+    }
+
+    async fn stop_poll(&mut self, global_state: &GlobalState) {
+        self.stop("dashboard", global_state);
+    }
+}
+
+impl VoteBot {
+    /** Run one of this bot's `COMMANDS`, the caller is allowed to run it */
+    async fn run_command(&mut self, name: &str, global_state: &GlobalState, msg: &PrivmsgMessage) {
+        match name {
+            "!poll" if command::args(msg).first().map(|arg| arg.to_lowercase()).as_deref() == Some("history") => {
+                let count = command::args(msg).get(1).and_then(|arg| arg.parse::<usize>().ok()).unwrap_or(5).clamp(1, 10);
+                let records = global_state.history.last(count);
+                let message = if records.is_empty() {
+                    "No polls finished yet".to_owned()
+                } else {
+                    let polls: Vec<String> = records.iter().enumerate()
+                        .map(|(i, record)| [(i + 1).to_string(), ". ".to_owned(), record.short()].concat())
+                        .collect();
+                    ["Last polls: ".to_owned(), polls.join(" | ")].concat()
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!poll" if command::args(msg).first().map(|arg| arg.to_lowercase()).as_deref() == Some("start") => {
+                let name = command::args(msg)[1..].join(" ");
+                let message = match self.config.presets.get(&name) {
+                    Some((name, preset)) => match preset.setup(&name) {
+                        Some(setup) => {
+                            self.open_poll(setup, &msg.sender.login, global_state);
+                            return;
+                        },
+                        None => ["The preset ".to_owned(), name, " needs a question, two options and a valid duration".to_owned()].concat(),
+                    },
+                    None => {
+                        let names = self.config.presets.names();
+                        if names.is_empty() {
+                            "There's no preset in the config".to_owned()
+                        } else {
+                            ["Presets: ".to_owned(), names.join(", ")].concat()
+                        }
+                    },
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!poll" if command::args(msg).first().map(|arg| arg.to_lowercase()).as_deref() == Some("reload") => {
+                let message = match bot::load_config().await {
+                    Ok(config) => {
+                        let count = self.config.presets.replace(config.vote_bot.presets);
+                        log::info(format!("[VoteBot] {} reloaded the presets", msg.sender.login));
+                        ["Reloaded ".to_owned(), count.to_string(), " presets".to_owned()].concat()
+                    },
+                    Err(err) => {
+                        log::error(format!("[VoteBot] Couldn't reload the presets\n{}", err));
+                        "Couldn't read the config, the presets stay as they were".to_owned()
+                    },
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!poll" if command::args(msg).first().map(|arg| arg.to_lowercase()).as_deref() == Some("queue") => {
+                self.run_queue_command(global_state, msg);
+            }
+            "!poll" => {
+                match parse_poll(&msg.message_text) {
+                    Some(setup) => {
+                        self.open_poll(setup, &msg.sender.login, global_state);
+                    },
+                    None => {
+                        global_state.outbox.say([
+                            "@".to_owned(),
+                            msg.sender.name.clone(),
+                            " Usage: ".to_owned(),
+                            POLL_USAGE.to_owned()
+                        ].concat());
+                    }
                 }
             }
-            "2" | "NO" => {
-                if self.state.can_vote(&msg.sender) {
-                    self.state.add_vote(1, 1, &msg.sender);
+            "!results" if self.state.is_secret && self.state.poll.is_counting => {
+                // Secret ballots count down to the reveal like they do when their time runs out
+                let countdown = self.config.reveal_countdown.min(5);
+                let force = is_forced(msg);
+                if countdown == 0 {
+                    self.close_poll(&msg.sender.login, force, global_state);
+                } else if self.state.revealed_by.is_none() {
+                    let reveal_at = chrono::offset::Local::now().timestamp_millis() + countdown * 1000;
+                    let closes_at = self.state.poll.closes_at.map_or(reveal_at, |closes_at| closes_at.min(reveal_at));
+                    self.state.revealed_by = Some((msg.sender.login.clone(), force));
+                    self.state.poll.set_closes_at(Some(closes_at), &[], &[]);
+                    global_state.display.publish(self.state.to_view());
+                    self.count_down(global_state);
                 }
             }
-            "!RESULTS_VOTES" => {
-                if is_mod(&msg) {
-                    self.state.stop_counting();
-                    let message = [
-                        "@".to_owned(),
-                        msg.sender.name.clone().to_owned(),
-                        " ".to_owned(),
-                        self.state.to_string()
-                    ].concat();
-                    client.say(global_state.channel_name.to_owned(), message).await.unwrap();
+            "!results" if self.state.poll.is_counting => {
+                self.close_poll(&msg.sender.login, is_forced(msg), global_state);
+            }
+            "!results" => {
+                let message = [
+                    "@".to_owned(),
+                    msg.sender.name.clone().to_owned(),
+                    " ".to_owned(),
+                    self.state.to_string()
+                ].concat();
+                global_state.outbox.say(message);
+            }
+            "!reset_votes" => {
+                let duration = command::args(msg).first().and_then(|arg| parse_duration(arg));
+                self.open_poll(PollSetup::yes_no(duration), &msg.sender.login, global_state);
+            }
+            "!extend" | "!shorten" => {
+                let duration = command::args(msg).first().and_then(|arg| parse_duration(arg));
+                let message = match duration {
+                    Some(_) if !self.state.poll.is_counting => "There's no poll running".to_owned(),
+                    Some(duration) => {
+                        let now = chrono::offset::Local::now().timestamp_millis();
+                        let closes_at = match (name, self.state.poll.closes_at) {
+                            ("!extend", Some(closes_at)) => Some(closes_at + duration * 1000),
+                            ("!extend", None) => Some(now + duration * 1000),
+                            (_, Some(closes_at)) => Some(closes_at - duration * 1000),
+                            (_, None) => None,
+                        };
+                        match closes_at {
+                            Some(closes_at) => {
+                                self.state.set_closes_at(Some(closes_at), &self.config);
+                                global_state.display.publish(self.state.to_view());
+                                [
+                                    "The poll now closes in ".to_owned(),
+                                    self.state.poll.seconds_left().unwrap_or(0).to_string(),
+                                    "s".to_owned()
+                                ].concat()
+                            },
+                            None => "This poll isn't timed, use !extend to give it a timer".to_owned(),
+                        }
+                    },
+                    None => ["Usage: ".to_owned(), name.to_owned(), " <duration>".to_owned()].concat(),
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!lastpoll" => {
+                let message = match global_state.history.last(1).first() {
+                    Some(record) => record.to_string(),
+                    None => "No polls finished yet".to_owned(),
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!suggest" => {
+                // Without the invisible characters and cheers, they'd get around the duplicate check
+                let text = visible_text(msg);
+                let text = text.split_once(char::is_whitespace)
+                    .map(|(_, text)| text)
+                    .unwrap_or("");
+                let message = match self.suggestions.add(text, &msg.sender) {
+                    Ok(number) => ["Got it, that's suggestion #".to_owned(), number.to_string()].concat(),
+                    // Don't answer everyone typing !suggest after it closed
+                    Err(_) if !self.suggestions.is_open => return,
+                    Err(error) => error,
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!suggestions" => {
+                let args = command::args(msg);
+                let message = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+                    None => ["Suggestions: ".to_owned(), self.suggestions.to_string()].concat(),
+                    Some("open") => {
+                        self.suggestions.open();
+                        global_state.outbox.say("Suggestions are open! Use !suggest <idea> to suggest an option for the next poll".to_owned());
+                        return;
+                    },
+                    Some("close") => {
+                        self.suggestions.is_open = false;
+                        ["Suggestions are closed. ".to_owned(), self.suggestions.to_string()].concat()
+                    },
+                    Some("poll") => {
+                        let labels = self.suggestions.approved();
+                        if labels.len() < 2 {
+                            "Approve at least 2 suggestions first".to_owned()
+                        } else {
+                            let text = msg.message_text.trim_start()
+                                .split_once(char::is_whitespace).map_or("", |(_, text)| text)
+                                .trim_start()
+                                .split_once(char::is_whitespace).map_or("", |(_, text)| text);
+                            let (mut setup, question) = parse_poll_prefix(text);
+                            let question = question.trim().trim_matches('"').trim();
+                            setup.question = if question.is_empty() { "Which one?" } else { question }.to_owned();
+                            setup.labels = labels;
+                            setup.range = None;
+                            // Rating polls don't have options to seed
+                            if setup.mode == PollMode::Rating {
+                                setup.mode = PollMode::Plurality;
+                            }
+                            self.suggestions.is_open = false;
+                            self.open_poll(setup, &msg.sender.login, global_state);
+                            return;
+                        }
+                    },
+                    Some(_) => ["Usage: ".to_owned(), SUGGESTIONS_USAGE.to_owned()].concat(),
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!approve" | "!reject" => {
+                let numbers: Vec<usize> = command::args(msg).iter()
+                    .filter_map(|arg| arg.trim_start_matches('#').parse().ok())
+                    .collect();
+                let status = if name == "!approve" { SuggestionStatus::Approved } else { SuggestionStatus::Rejected };
+                let message = match self.suggestions.set_status(&numbers, status) {
+                    0 => ["Usage: ".to_owned(), name.to_owned(), " <number> ..., see !suggestions for the numbers".to_owned()].concat(),
+                    _ => ["Suggestions: ".to_owned(), self.suggestions.to_string()].concat(),
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+            }
+            "!pick" => {
+                let number = command::args(msg).first().and_then(|arg| arg.parse::<usize>().ok());
+                let mut picked = false;
+                let message = match number.and_then(|number| number.checked_sub(1)) {
+                    _ if self.state.awaiting_pick.is_empty() => "There's no tie to break".to_owned(),
+                    Some(pick) if self.state.awaiting_pick.contains(&pick) => {
+                        self.state.poll.decided = Some(pick);
+                        self.state.awaiting_pick = Vec::new();
+                        picked = true;
+                        global_state.display.publish(self.state.to_view());
+                        [self.state.poll.options[pick].label(), " wins!".to_owned()].concat()
+                    },
+                    _ => {
+                        let numbers: Vec<String> = self.state.awaiting_pick.iter().map(|i| (i + 1).to_string()).collect();
+                        ["Pick one of ".to_owned(), numbers.join(", ")].concat()
+                    },
+                };
+                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+                if picked {
+                    // It was saved without a winner when it closed
+                    global_state.history.update(&self.state.to_record());
+                    self.follow_up(global_state);
                 }
             }
-            "!RESET_VOTES" => {
-                if is_mod(&msg) {
-                    self.state.reset();
-                    client.say(
-                        global_state.channel_name.to_owned(), 
-                        "Reset votes! Vote Yes with 1 and No with 2!".to_owned()
-                    ).await.unwrap();
+            "!veto" | "!override" => {
+                let pick = match &self.state.poll.pending {
+                    Some(pending) => pending.pick,
+                    None => {
+                        global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " There's no result waiting for confirmation".to_owned()].concat());
+                        return;
+                    },
+                };
+                let args = command::args(msg);
+                let replacement = if name == "!override" {
+                    match self.state.poll.option_named(&args.join(" ")) {
+                        Some(option) => Some(option),
+                        None => {
+                            global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " Usage: !override <option>".to_owned()].concat());
+                            return;
+                        },
+                    }
+                } else {
+                    match Veto::from_keyword(args.first().copied()) {
+                        Some(Veto::Rerun) => None,
+                        Some(Veto::RunnerUp) => match self.state.poll.runner_up(pick) {
+                            Some(runner_up) => Some(runner_up),
+                            None => {
+                                global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " There's no runner-up".to_owned()].concat());
+                                return;
+                            },
+                        },
+                        None => {
+                            global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " Usage: !veto [rerun|runner_up]".to_owned()].concat());
+                            return;
+                        },
+                    }
+                };
+                self.state.poll.pending = None;
+                let vetoed = self.state.poll.options[pick].label();
+                match replacement {
+                    Some(option) => {
+                        let label = self.state.poll.options[option].label();
+                        global_state.audit.record(self.name(), &msg.sender.login, [
+                            "overrode ".to_owned(), vetoed.clone(), " with ".to_owned(), label.clone(),
+                            " in \"".to_owned(), self.state.question.clone(), "\"".to_owned()
+                        ].concat());
+                        self.state.poll.decided = Some(option);
+                        let mut record = self.state.to_record();
+                        record.veto = Some([vetoed.clone(), " replaced with ".to_owned(), label.clone(), " by ".to_owned(), msg.sender.login.clone()].concat());
+                        global_state.history.save(&record);
+                        global_state.display.publish(self.state.to_view());
+                        global_state.outbox.say([vetoed, " got overridden, ".to_owned(), label, " wins!".to_owned()].concat());
+                        self.follow_up(global_state);
+                    },
+                    None => {
+                        global_state.audit.record(self.name(), &msg.sender.login, [
+                            "vetoed ".to_owned(), vetoed.clone(), " and reran \"".to_owned(),
+                            self.state.question.clone(), "\"".to_owned()
+                        ].concat());
+                        // The vetoed poll stays in the history, without a winner
+                        let mut record = self.state.to_record();
+                        record.winner = None;
+                        record.veto = Some([vetoed.clone(), " vetoed by ".to_owned(), msg.sender.login.clone(), ", voted again".to_owned()].concat());
+                        global_state.history.save(&record);
+                        global_state.outbox.say([vetoed, " got vetoed, vote again!".to_owned()].concat());
+                        let setup = self.state.rerun_setup();
+                        self.open_poll(setup, &msg.sender.login, global_state);
+                    },
                 }
             }
-            "!STOP_VOTES" => {
-                if is_mod(&msg) {
-                    self.state.stop_counting();
-                    client.say(
-                        global_state.channel_name.to_owned(), 
-                        "Stopped counting!".to_owned()
-                    ).await.unwrap();
+            "!unvote" => {
+                // Taking a vote back would be a way around not changing it
+                if !self.config.allow_vote_change || !self.state.poll.is_counting {
+                    return;
                 }
+                if self.state.poll.remove_vote(&msg.sender) {
+                    global_state.display.publish(self.state.to_view());
+                }
+            }
+            "!stop_votes" => {
+                self.stop(&msg.sender.login, global_state);
             }
             _ => {}
         }
     }
+    /** `!poll queue` and what follows it */
+    fn run_queue_command(&self, global_state: &GlobalState, msg: &PrivmsgMessage) {
+        let queue = &global_state.queue;
+        let args = command::args(msg);
+        let numbers: Vec<usize> = args.iter().skip(2).filter_map(|arg| arg.parse().ok()).collect();
+        let message = match (args.get(1).map(|arg| arg.to_lowercase()).as_deref(), numbers.as_slice()) {
+            (None, _) => {
+                let polls: Vec<String> = queue.list().iter().enumerate()
+                    .map(|(i, queued)| [(i + 1).to_string(), ". ".to_owned(), queued.question.clone()].concat())
+                    .collect();
+                if polls.is_empty() {
+                    "The queue is empty".to_owned()
+                } else {
+                    ["Queued polls: ".to_owned(), polls.join(" | ")].concat()
+                }
+            },
+            (Some("add"), _) => {
+                let poll = skip_words(&msg.message_text, 3);
+                match self.config.presets.setup_for(poll) {
+                    Some(setup) => {
+                        let position = queue.push(QueuedPoll {
+                            question: setup.question.clone(),
+                            poll: poll.to_owned(),
+                            added_by: msg.sender.login.clone(),
+                        });
+                        ["Queued \"".to_owned(), setup.question, "\" at #".to_owned(), position.to_string()].concat()
+                    },
+                    None => "Usage: !poll queue add <preset> or !poll queue add [settings] \"<question>\" | <option> | <option> ..., settings are the same as !poll's".to_owned(),
+                }
+            },
+            (Some("remove"), [position]) => match queue.remove(*position) {
+                Some(queued) => ["Removed \"".to_owned(), queued.question, "\" from the queue".to_owned()].concat(),
+                None => ["There's no #".to_owned(), position.to_string(), " in the queue".to_owned()].concat(),
+            },
+            (Some("move"), [from, to]) => if queue.move_to(*from, *to) {
+                ["Moved #".to_owned(), from.to_string(), " to #".to_owned(), to.to_string()].concat()
+            } else {
+                "Both positions need to be in the queue".to_owned()
+            },
+            (Some("clear"), _) => ["Cleared ".to_owned(), queue.clear().to_string(), " queued polls".to_owned()].concat(),
+            _ => ["Usage: ".to_owned(), QUEUE_USAGE.to_owned()].concat(),
+        };
+        global_state.outbox.say(["@".to_owned(), msg.sender.name.clone(), " ".to_owned(), message].concat());
+    }
+    /** Queue the scheduled polls that are due, unless they're still waiting from last time */
+    fn queue_scheduled(&mut self, now: i64, global_state: &GlobalState) {
+        for scheduled in self.schedule.iter_mut().filter(|scheduled| now >= scheduled.next_at) {
+            scheduled.next_at = now + scheduled.every * 1000;
+            if !global_state.queue.contains(&scheduled.poll) {
+                global_state.queue.push(QueuedPoll {
+                    question: scheduled.question.clone(),
+                    poll: scheduled.poll.clone(),
+                    added_by: "schedule".to_owned(),
+                });
+            }
+        }
+    }
+    /** Start the next queued poll once the last one had its time on screen */
+    fn start_queued(&mut self, now: i64, global_state: &GlobalState) {
+        let is_busy = self.state.poll.pending.is_some() || !self.state.awaiting_pick.is_empty();
+        if is_busy || now < self.state.poll.closed_at + self.config.queue_gap * 1000 {
+            return;
+        }
+        let queued = match global_state.queue.pop() {
+            Some(queued) => queued,
+            None => return,
+        };
+        match self.config.presets.setup_for(&queued.poll) {
+            Some(setup) => {
+                self.open_poll(setup, &queued.added_by, global_state);
+            },
+            None => log::error(format!("[VoteBot] Skipped the queued poll {:?}, couldn't parse it", queued.poll)),
+        }
+    }
+    /** Stop counting without telling the results */
+    fn stop(&mut self, by: &str, global_state: &GlobalState) {
+        if self.state.poll.close(by) {
+            self.save_history(global_state);
+        }
+        global_state.display.publish(self.state.to_view());
+        global_state.outbox.say("Stopped counting!".to_owned());
+    }
+    /** Do what the poll's preset asks for once the poll is decided */
+    fn follow_up(&self, global_state: &GlobalState) {
+        let preset = match self.state.preset.as_ref().and_then(|name| self.config.presets.get(name)) {
+            Some((_, preset)) => preset,
+            None => return,
+        };
+        if let Some(message) = &preset.then_say {
+            let winner = self.state.winner().map(|option| option.label()).unwrap_or_else(|| "nobody".to_owned());
+            global_state.outbox.say(message.replace("{winner}", &winner));
+        }
+        if let Some(next) = &preset.then_start {
+            match self.config.presets.get(next).and_then(|(name, preset)| preset.setup(&name)) {
+                Some(setup) => global_state.queue.push_front(QueuedPoll {
+                    question: setup.question,
+                    poll: next.clone(),
+                    added_by: "preset".to_owned(),
+                }),
+                None => log::error(format!("[VoteBot] Couldn't start the preset {:?} after {:?}", next, self.state.question)),
+            }
+        }
+    }
+    /** Keep the poll that just closed in the history */
+    fn save_history(&self, global_state: &GlobalState) {
+        global_state.history.save(&self.state.to_record());
+    }
+    /** Count down the last seconds of a timed secret ballot, starts over if the poll gets extended */
+    fn count_down(&mut self, global_state: &GlobalState) {
+        let countdown = self.config.reveal_countdown.min(5);
+        let seconds_left = match self.state.poll.seconds_left() {
+            Some(seconds_left) if countdown > 0 => seconds_left,
+            _ => return,
+        };
+        if seconds_left > countdown {
+            self.state.countdown_said = 0;
+        } else if seconds_left > 0 && (self.state.countdown_said == 0 || seconds_left < self.state.countdown_said) {
+            self.state.countdown_said = seconds_left;
+            global_state.outbox.say(["Results in ".to_owned(), seconds_left.to_string(), "...".to_owned()].concat());
+        }
+    }
+    /** Tell chat how the poll is going, unless nothing changed since last time */
+    fn announce_progress(&mut self, global_state: &GlobalState) {
+        let progress = match self.state.progress() {
+            Some(progress) if progress != self.state.last_progress => progress,
+            _ => return,
+        };
+        let mut message = progress.clone();
+        if let Some(seconds_left) = self.state.poll.seconds_left() {
+            message = [message, " with ".to_owned(), seconds_left.to_string(), "s left".to_owned()].concat();
+        }
+        // Skipped when chat is busy, the results matter more
+        if global_state.outbox.say_low_priority(message) {
+            self.state.last_progress = progress;
+        }
+    }
+    /**
+    Start a poll and tell chat how to vote on it. A result still waiting for
+    mods is final once another poll starts, it gets saved and followed up first.
+    */
+    fn open_poll(&mut self, setup: PollSetup, by: &str, global_state: &GlobalState) {
+        if self.state.poll.pending.take().is_some() {
+            self.save_history(global_state);
+            self.follow_up(global_state);
+        }
+        self.state.start(setup, &self.config, by);
+        self.announce_poll(global_state);
+    }
+    /** Tell chat a poll started and how to vote on it */
+    fn announce_poll(&mut self, global_state: &GlobalState) {
+        self.state.owns_numbers = global_state.numbers.sync(self.name(), self.state.poll.is_counting);
+        global_state.display.publish(self.state.to_view());
+        let mut strings = vec![
+            self.state.question.clone(),
+            " ".to_owned(),
+            self.state.vote_instructions()
+        ];
+        if let Some(seconds_left) = self.state.poll.seconds_left() {
+            strings.push([" You have ".to_owned(), seconds_left.to_string(), "s.".to_owned()].concat());
+        }
+        if let Some(who) = self.state.poll.eligibility.summary() {
+            strings.push([" ".to_owned(), who, ".".to_owned()].concat());
+        }
+        global_state.outbox.say(strings.concat());
+    }
+    /**
+    Close the poll and tell chat who won, the timer and `!results` both end up
+    here. The quorum applies either way unless a mod `force`s the results.
+    */
+    fn close_poll(&mut self, by: &str, force: bool, global_state: &GlobalState) {
+        // A secret ballot someone asked the results of is done counting down
+        let (by, force) = self.state.revealed_by.take().unwrap_or_else(|| (by.to_owned(), force));
+        if !force {
+            let active_chatters = self.active_chatters();
+            if let Some(reason) = self.state.quorum_failure(active_chatters) {
+                self.fail_quorum(reason, &by, global_state);
+                return;
+            }
+        }
+        self.state.poll.close(&by);
+        let mut strings = vec!["Poll closed! ".to_owned(), self.state.to_string()];
+        let mut runoff = None;
+        match (self.state.poll.outcome(), self.state.mode) {
+            // The runoff summary already names the winner and ratings don't have one
+            (Outcome::Winner(_), PollMode::RankedChoice) | (_, PollMode::Rating) => {},
+            (Outcome::Winner(winner), _) => strings.push([
+                ". Winner: ".to_owned(),
+                self.state.poll.options[winner].label(),
+                "!".to_owned()
+            ].concat()),
+            (Outcome::NoVotes, _) => strings.push(". Nobody voted!".to_owned()),
+            (Outcome::Tie(tied), _) => {
+                let (message, setup) = self.break_tie(tied);
+                strings.push(". ".to_owned());
+                strings.push(message);
+                runoff = setup;
+            },
+        }
+        let held = self.hold_result(global_state);
+        let is_held = held.is_some();
+        strings.extend(held);
+        global_state.display.publish(self.state.to_view());
+        global_state.outbox.say(strings.concat());
+        if let Some(setup) = runoff {
+            self.state.start(setup, &self.config, "VoteBot");
+            self.state.is_runoff = true;
+            self.announce_poll(global_state);
+        } else if !is_held && self.state.awaiting_pick.is_empty() {
+            self.follow_up(global_state);
+        }
+    }
+    /**
+    The winner is only final once mods had their chance to veto it, returns
+    what to tell chat about it. Results that don't wait get saved right away.
+    */
+    fn hold_result(&mut self, global_state: &GlobalState) -> Option<String> {
+        match self.state.winner_index() {
+            Some(winner) if self.config.confirm_seconds > 0 => {
+                self.state.poll.hold(winner, self.config.confirm_seconds);
+                Some([
+                    " Mods have ".to_owned(),
+                    self.config.confirm_seconds.to_string(),
+                    "s to !veto or !override it.".to_owned()
+                ].concat())
+            },
+            _ => {
+                self.save_history(global_state);
+                None
+            },
+        }
+    }
+    /** Chatters that said something in the last `active_minutes` */
+    fn active_chatters(&mut self) -> usize {
+        let since = chrono::offset::Local::now().timestamp_millis() - self.config.quorum.active_minutes * 60 * 1000;
+        self.last_seen.retain(|_, &mut at| at >= since);
+        self.last_seen.len()
+    }
+    /** Extend a poll that missed its quorum, or close it with the default option */
+    fn fail_quorum(&mut self, reason: String, by: &str, global_state: &GlobalState) {
+        let quorum = &self.config.quorum;
+        if quorum.fallback == QuorumFallback::Extend && self.state.extensions < quorum.max_extensions {
+            let now = chrono::offset::Local::now().timestamp_millis();
+            self.state.extensions += 1;
+            self.state.set_closes_at(Some(now + quorum.extend_by * 1000), &self.config);
+            global_state.display.publish(self.state.to_view());
+            // The reason could give away who's leading
+            let reason = if self.state.is_secret { "The quorum isn't reached".to_owned() } else { reason };
+            global_state.outbox.say([
+                reason,
+                ", the poll gets ".to_owned(),
+                quorum.extend_by.to_string(),
+                "s more! ".to_owned(),
+                self.state.question.clone(),
+                " ".to_owned(),
+                self.state.vote_instructions()
+            ].concat());
+            return;
+        }
+        self.state.poll.close(by);
+        self.state.failed_quorum = true;
+        if self.state.mode != PollMode::Rating {
+            self.state.poll.decided = quorum.default_option.checked_sub(1).filter(|&i| i < self.state.poll.options.len());
+        }
+        let fallback = match self.state.winner() {
+            Some(option) => [". Going with the default: ".to_owned(), option.label(), "!".to_owned()].concat(),
+            None => ". No decision this time!".to_owned(),
+        };
+        let held = self.hold_result(global_state);
+        let is_held = held.is_some();
+        global_state.display.publish(self.state.to_view());
+        global_state.outbox.say([
+            "Poll closed! ".to_owned(),
+            self.state.to_string(),
+            ". ".to_owned(),
+            reason,
+            fallback,
+            held.unwrap_or_default()
+        ].concat());
+        if !is_held {
+            self.follow_up(global_state);
+        }
+    }
+    /**
+    Apply the tie policy, returns what to tell chat and the poll to start if
+    the tie goes to a re-vote.
+    */
+    fn break_tie(&mut self, tied: Vec<usize>) -> (String, Option<PollSetup>) {
+        let tie = ["It's a tie between ".to_owned(), self.state.poll.labels_of(&tied), "!".to_owned()].concat();
+        // Runoffs that tie again would go on forever
+        let policy = match self.config.tie_policy {
+            TiePolicy::Runoff if self.state.is_runoff => TiePolicy::Random,
+            policy => policy,
+        };
+        match policy {
+            TiePolicy::Random => {
+                self.state.poll.decided = tally::pick_random(&tied);
+                let pick = self.state.winner().map(|option| option.label()).unwrap_or_default();
+                ([tie, " Picked at random: ".to_owned(), pick, "!".to_owned()].concat(), None)
+            },
+            TiePolicy::Default => {
+                self.state.poll.decided = self.config.tie_default.checked_sub(1)
+                    .filter(|&i| i < self.state.poll.options.len())
+                    .or_else(|| tied.first().copied());
+                let pick = self.state.winner().map(|option| option.label()).unwrap_or_default();
+                ([tie, " Going with the default: ".to_owned(), pick, "!".to_owned()].concat(), None)
+            },
+            TiePolicy::StreamerDecides => {
+                let numbers: Vec<String> = tied.iter().map(|i| (i + 1).to_string()).collect();
+                self.state.awaiting_pick = tied;
+                ([tie, " The streamer decides with !pick ".to_owned(), numbers.join(" or ")].concat(), None)
+            },
+            TiePolicy::Runoff => {
+                let labels = tied.iter().map(|&i| self.state.poll.options[i].label()).collect();
+                let setup = PollSetup {
+                    // A ranked or approval re-vote could tie the same way again
+                    mode: PollMode::Plurality,
+                    labels,
+                    range: None,
+                    ..self.state.rerun_setup()
+                };
+                ([tie, " Time for a runoff!".to_owned()].concat(), Some(setup))
+            },
+        }
+    }
+}
 
-    async fn update(&mut self, _global_state: &GlobalState, _client: &twitch_irc::TwitchIRCClient<twitch_irc::SecureTCPTransport, twitch_irc::login::StaticLoginCredentials>) {
-        //println!("Vote Bot Updated");
+/** `!results force` closes the poll whatever the quorum says */
+fn is_forced(msg: &PrivmsgMessage) -> bool {
+    matches!(command::args(msg).first(), Some(arg) if arg.eq_ignore_ascii_case("force"))
+}
+
+/**
+Parse a `!poll ranked 60s "Which map?" | Dust | Mirage | Inferno` message. The
+mode and duration are optional, in any order, but there needs to be at least
+two options. Rating polls like `!poll rating 0-5 "How was that?"` don't take
+options but can be given a range.
+*/
+fn parse_poll(text: &str) -> Option<PollSetup> {
+    let (_, text) = text.trim_start().split_once(char::is_whitespace)?;
+    parse_poll_args(text)
+}
+
+/** Same as `parse_poll` without the `!poll`, like the polls of the queue and the schedule */
+pub fn parse_poll_args(text: &str) -> Option<PollSetup> {
+    let (mut setup, text) = parse_poll_prefix(text);
+    let mut parts = text.split('|').map(|part| part.trim());
+    setup.question = parts.next()?.trim_matches('"').trim().to_owned();
+    setup.labels = parts
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_owned())
+        .collect();
+    if setup.question.is_empty() || (setup.labels.len() < 2 && setup.mode != PollMode::Rating) {
+        return None;
+    }
+    if setup.mode == PollMode::Rating {
+        setup.labels = Vec::new();
+    } else {
+        setup.range = None;
+    }
+    Some(setup)
+}
+
+/**
+Take the settings (mode, duration, range, quorum, strictness and eligibility)
+off the start of a poll's arguments, in any order. Returns them without a
+question or options, with whatever is left of the text. Durations need a
+unit here, `!poll 5 or 6? | 5 | 6` asks about 5 instead of lasting 5 seconds.
+*/
+fn parse_poll_prefix(mut text: &str) -> (PollSetup, &str) {
+    let mut setup = PollSetup::default();
+    loop {
+        let (first, rest) = text.trim_start().split_once(char::is_whitespace)
+            .unwrap_or((text.trim_start(), ""));
+        let has_unit = first.contains(char::is_alphabetic);
+        if let Some(seconds) = parse_duration(first).filter(|_| has_unit) {
+            setup.duration = Some(seconds);
+        } else if let Some(rating_range) = parse_range(first) {
+            setup.range = Some(rating_range);
+        } else if let Some(mode) = PollMode::from_keyword(first) {
+            setup.mode = mode;
+        } else if let Some(strictness) = Strictness::from_keyword(first) {
+            setup.strictness = Some(strictness);
+        } else if first.eq_ignore_ascii_case("secret") {
+            setup.secret = true;
+        } else if let Some(rules) = first.strip_prefix("only=") {
+            if !rules.split(',').all(|rule| setup.eligibility.enable(rule)) {
+                break;
+            }
+        } else if !parse_quorum(first, &mut setup.quorum) {
+            break;
+        }
+        text = rest;
+    }
+    (setup, text)
+}
+
+/** What's left of the text after its first `count` words */
+fn skip_words(mut text: &str, count: usize) -> &str {
+    for _ in 0..count {
+        text = text.trim_start();
+        text = &text[text.find(char::is_whitespace).unwrap_or(text.len())..];
     }
-}
\ No newline at end of file
+    text.trim()
+}
+
+/** Parse `quorum=10`, `quorum=25%` or `majority=60%` into the rules, returns false if it's none of them */
+fn parse_quorum(text: &str, rules: &mut QuorumRules) -> bool {
+    let (key, value) = match text.split_once('=') {
+        Some(pair) => pair,
+        None => return false,
+    };
+    let share = value.strip_suffix('%')
+        .and_then(|percent| percent.parse::<f64>().ok())
+        .filter(|percent| (0.0..=100.0).contains(percent))
+        .map(|percent| percent / 100.0);
+    match (key.to_lowercase().as_str(), share) {
+        ("quorum", Some(share)) => rules.min_active_share = Some(share),
+        ("quorum", None) => match value.parse::<usize>() {
+            Ok(min_voters) => rules.min_voters = Some(min_voters),
+            Err(_) => return false,
+        },
+        ("majority" | "supermajority", Some(share)) => rules.supermajority = Some(share),
+        _ => return false,
+    }
+    true
+}
+
+/** `0.25` -> `25%` */
+fn percent(share: f64) -> String {
+    [format_number(share * 100.0), "%".to_owned()].concat()
+}
+
+/** Parse a rating range like `1-10` or `0.5-5` */
+fn parse_range(text: &str) -> Option<(f64, f64)> {
+    let (min, max) = text.split_once('-')?;
+    let (min, max) = (min.parse::<f64>().ok()?, max.parse::<f64>().ok()?);
+    if min.is_finite() && max.is_finite() && min < max {
+        Some((min, max))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_of_a_poll_need_a_unit() {
+        let setup = parse_poll_args("5 or 6? | 5 | 6").unwrap();
+        assert_eq!(setup.duration, None);
+        assert_eq!(setup.question, "5 or 6?");
+        let setup = parse_poll_args("90s \"5 or 6?\" | 5 | 6").unwrap();
+        assert_eq!(setup.duration, Some(90));
+        assert_eq!(setup.question, "5 or 6?");
+        assert_eq!(parse_poll_args("1m30s ranked Which map? | Dust | Mirage").unwrap().duration, Some(90));
+    }
+}
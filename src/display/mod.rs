@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+pub mod overlay;
+pub mod tui;
+
+/** How many chat lines are kept around for the dashboard */
+const MAX_CHAT_LINES: usize = 50;
+
+/** A single option of a poll as it should be displayed */
+#[derive(Serialize, Clone, Debug)]
+pub struct OptionView {
+    pub label: String,
+    /** Weighted total */
+    pub votes: i32,
+    pub voters: i32,
+}
+
+/** Statistics of a rating poll, its options are the histogram buckets */
+#[derive(Serialize, Clone, Debug)]
+pub struct RatingView {
+    pub count: usize,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+}
+
+/**
+Snapshot of a bot's poll. Bots publish one of these every time their voting
+state changes and the displays (overlay, etc) render whatever they get.
+*/
+#[derive(Serialize, Clone, Debug)]
+pub struct PollView {
+    pub bot: String,
+    pub question: String,
+    pub options: Vec<OptionView>,
+    pub is_open: bool,
+    /** Timestamp (ms) of when the poll closes by itself, if it ever does */
+    pub closes_at: Option<i64>,
+    pub winner: Option<String>,
+    pub rating: Option<RatingView>,
+    /** Voters so far of a secret ballot, its options don't have votes until it closes */
+    pub secret_voters: Option<usize>,
+}
+
+/**
+Shared hub between the bots and anything displaying their state. Keeps the
+latest view of every bot so late subscribers can catch up, along with a few
+odds and ends only the dashboard cares about.
+*/
+#[derive(Clone)]
+pub struct Display {
+    polls: Arc<Mutex<HashMap<String, PollView>>>,
+    tx: broadcast::Sender<PollView>,
+    statuses: Arc<Mutex<BTreeMap<String, String>>>,
+    chat: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Display {
+    /** Store the view as the latest one for its bot and push it to subscribers */
+    pub fn publish(&self, view: PollView) {
+        self.polls.lock().unwrap().insert(view.bot.clone(), view.clone());
+        // Nobody listening is fine, the view is still stored
+        let _ = self.tx.send(view);
+    }
+    /** Returns the latest view of every bot and a receiver for the next ones */
+    pub fn subscribe(&self) -> (Vec<PollView>, broadcast::Receiver<PollView>) {
+        let polls = self.polls.lock().unwrap();
+        (polls.values().cloned().collect(), self.tx.subscribe())
+    }
+    /** Latest view of every bot */
+    pub fn polls(&self) -> Vec<PollView> {
+        let mut polls: Vec<PollView> = self.polls.lock().unwrap().values().cloned().collect();
+        polls.sort_by(|a, b| a.bot.cmp(&b.bot));
+        polls
+    }
+    /** Set a line of status, like `"Twitch" -> "Connected"` */
+    pub fn set_status(&self, name: &str, status: String) {
+        self.statuses.lock().unwrap().insert(name.to_owned(), status);
+    }
+    pub fn statuses(&self) -> Vec<(String, String)> {
+        self.statuses.lock().unwrap().iter()
+            .map(|(name, status)| (name.clone(), status.clone()))
+            .collect()
+    }
+    /** Remember a chat line for the dashboard */
+    pub fn push_chat(&self, line: String) {
+        let mut chat = self.chat.lock().unwrap();
+        if chat.len() >= MAX_CHAT_LINES {
+            chat.pop_front();
+        }
+        chat.push_back(line);
+    }
+    /** The most recent chat lines, oldest first */
+    pub fn recent_chat(&self) -> Vec<String> {
+        self.chat.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self {
+            polls: Default::default(),
+            tx,
+            statuses: Default::default(),
+            chat: Default::default(),
+        }
+    }
+}
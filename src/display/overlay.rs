@@ -0,0 +1,243 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, ORIGIN};
+use hyper::service::{make_service_fn, service_fn};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::bots::vote_bot::Presets;
+use crate::display::{Display, PollView};
+use crate::util::log;
+use crate::util::queue::{PollQueue, QueuedPoll};
+
+const OVERLAY_PAGE: &str = include_str!("overlay.html");
+
+/** What the admin API needs, it's off without a token */
+#[derive(Clone)]
+pub struct Admin {
+    pub queue: PollQueue,
+    /** Queued polls can be a preset's name like they can in chat */
+    pub presets: Presets,
+    /** Every request has to send it as `Authorization: Bearer <token>` */
+    pub token: Option<String>,
+    pub port: u16,
+}
+
+/** Body of `POST /queue` */
+#[derive(Deserialize)]
+struct QueueRequest {
+    poll: String,
+}
+
+/** Body of `POST /queue/<n>/move` */
+#[derive(Deserialize)]
+struct MoveRequest {
+    to: usize,
+}
+
+/**
+Serve the overlay page for OBS browser sources on `127.0.0.1:<port>`. The page
+is at `/` and the poll updates are pushed as Server-Sent Events on `/events`.
+The poll queue's admin API is on `/queue`, it's only reachable from this
+machine and only with the configured token.
+*/
+pub async fn serve(display: Display, admin: Admin) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], admin.port));
+    let make_service = make_service_fn(move |_| {
+        let display = display.clone();
+        let admin = admin.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle(display.clone(), admin.clone(), req)))
+        }
+    });
+    log::info(format!("[Overlay] Serving overlay on http://{}", addr));
+    if let Err(err) = Server::bind(&addr).serve(make_service).await {
+        log::error(format!("[Overlay] Server stopped\n{}", err));
+    }
+}
+
+async fn handle(display: Display, admin: Admin, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if is_admin_path(req.uri().path()) {
+        return Ok(admin_api(admin, req).await.unwrap());
+    }
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/") => Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(Body::from(OVERLAY_PAGE)),
+        (&Method::GET, "/events") => Response::builder()
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .body(events(display)),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    Ok(response.unwrap())
+}
+
+/**
+The queue's admin API:
+- `GET /queue` lists the queued polls as JSON
+- `POST /queue` queues `{"poll": "..."}`, written like it would follow `!poll`
+- `POST /queue/<n>/move` moves poll `n` to `{"to": <position>}`
+- `DELETE /queue/<n>` removes poll `n`, `DELETE /queue` all of them
+
+Requests need the token and bodies have to be JSON, browsers can't send
+those to another site without asking it first. Requests from a page of
+another site get turned away too.
+*/
+async fn admin_api(admin: Admin, req: Request<Body>) -> Result<Response<Body>, hyper::http::Error> {
+    let token = match &admin.token {
+        Some(token) if !token.is_empty() => token,
+        _ => return status(StatusCode::NOT_FOUND),
+    };
+    if let Some(origin) = req.headers().get(ORIGIN) {
+        let allowed = [
+            ["http://127.0.0.1:".to_owned(), admin.port.to_string()].concat(),
+            ["http://localhost:".to_owned(), admin.port.to_string()].concat(),
+        ];
+        if !allowed.iter().any(|allowed| origin.as_bytes() == allowed.as_bytes()) {
+            return status(StatusCode::FORBIDDEN);
+        }
+    }
+    let bearer = ["Bearer ".to_owned(), token.clone()].concat();
+    if !matches!(req.headers().get(AUTHORIZATION), Some(header) if same_bytes(header.as_bytes(), bearer.as_bytes())) {
+        return status(StatusCode::UNAUTHORIZED);
+    }
+    let is_json = matches!(
+        req.headers().get(CONTENT_TYPE).and_then(|header| header.to_str().ok()),
+        Some(content_type) if content_type.trim_start().to_lowercase().starts_with("application/json")
+    );
+    let method = req.method().clone();
+    if method == Method::POST && !is_json {
+        return status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    let queue = admin.queue;
+    let path: Vec<String> = req.uri().path().split('/').filter(|part| !part.is_empty()).map(|part| part.to_owned()).collect();
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return status(StatusCode::BAD_REQUEST),
+    };
+    let position = path.get(1).and_then(|part| part.parse::<usize>().ok());
+    match (method, position, path.get(2).map(|part| part.as_str()), path.len()) {
+        (Method::GET, None, None, 1) => json(StatusCode::OK, &queue.list()),
+        (Method::POST, None, None, 1) => {
+            let poll = match serde_json::from_slice::<QueueRequest>(&body) {
+                Ok(request) => request.poll.trim().to_owned(),
+                Err(_) => return status(StatusCode::BAD_REQUEST),
+            };
+            match admin.presets.setup_for(&poll) {
+                Some(setup) => {
+                    let queued = QueuedPoll { question: setup.question, poll, added_by: "api".to_owned() };
+                    queue.push(queued.clone());
+                    log::info(format!("[Overlay] Queued {:?} from the admin API", queued.question));
+                    json(StatusCode::CREATED, &queued)
+                },
+                None => status(StatusCode::BAD_REQUEST),
+            }
+        },
+        (Method::POST, Some(from), Some("move"), 3) => match serde_json::from_slice::<MoveRequest>(&body) {
+            Ok(request) if queue.move_to(from, request.to) => json(StatusCode::OK, &queue.list()),
+            Ok(_) => status(StatusCode::NOT_FOUND),
+            Err(_) => status(StatusCode::BAD_REQUEST),
+        },
+        (Method::DELETE, None, None, 1) => {
+            queue.clear();
+            status(StatusCode::NO_CONTENT)
+        },
+        (Method::DELETE, Some(position), None, 2) => match queue.remove(position) {
+            Some(_) => status(StatusCode::NO_CONTENT),
+            None => status(StatusCode::NOT_FOUND),
+        },
+        _ => status(StatusCode::NOT_FOUND),
+    }
+}
+
+/** `/queue`, `/queue/<n>` or `/queue/<n>/move`, anything else isn't the admin API's */
+fn is_admin_path(path: &str) -> bool {
+    let rest = match path.strip_prefix("/queue") {
+        Some("") => return true,
+        Some(rest) => rest,
+        None => return false,
+    };
+    let rest = match rest.strip_prefix('/') {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let position = rest.strip_suffix("/move").unwrap_or(rest);
+    !position.is_empty() && position.bytes().all(|b| b.is_ascii_digit())
+}
+
+/** Compare in a time that doesn't tell how much of the token was right */
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn json<T: serde::Serialize>(code: StatusCode, value: &T) -> Result<Response<Body>, hyper::http::Error> {
+    Response::builder()
+        .status(code)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(value).unwrap()))
+}
+
+fn status(code: StatusCode) -> Result<Response<Body>, hyper::http::Error> {
+    Response::builder().status(code).body(Body::empty())
+}
+
+/** Body streaming every poll view as an SSE event until the page goes away */
+fn events(display: Display) -> Body {
+    let (mut sender, body) = Body::channel();
+    let (current, mut rx) = display.subscribe();
+    tokio::spawn(async move {
+        for view in current {
+            if sender.send_data(to_event(&view).into()).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            match rx.recv().await {
+                Ok(view) => {
+                    if sender.send_data(to_event(&view).into()).await.is_err() {
+                        return;
+                    }
+                },
+                // Missing a few in-between views is fine, the next one is complete
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+    body
+}
+
+fn to_event(view: &PollView) -> String {
+    ["data: ".to_owned(), serde_json::to_string(view).unwrap(), "\n\n".to_owned()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_queue_paths_go_to_the_admin_api() {
+        assert!(is_admin_path("/queue"));
+        assert!(is_admin_path("/queue/2"));
+        assert!(is_admin_path("/queue/2/move"));
+        assert!(!is_admin_path("/queuefoo"));
+        assert!(!is_admin_path("/queue/"));
+        assert!(!is_admin_path("/queue/../events"));
+        assert!(!is_admin_path("/queue/2/delete"));
+        assert!(!is_admin_path("/queue/move"));
+        assert!(!is_admin_path("/"));
+    }
+
+    #[test]
+    fn tokens_have_to_match_byte_for_byte() {
+        assert!(same_bytes(b"Bearer abc", b"Bearer abc"));
+        assert!(!same_bytes(b"Bearer abd", b"Bearer abc"));
+        assert!(!same_bytes(b"Bearer ab", b"Bearer abc"));
+        assert!(!same_bytes(b"", b"Bearer abc"));
+    }
+}
@@ -0,0 +1,252 @@
+use std::io;
+use std::time::Duration;
+
+use chrono::TimeZone;
+use chrono::offset::Local;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode};
+use tokio::time::interval;
+use tui::Terminal;
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph};
+
+use crate::display::{OptionView, PollView};
+use crate::util::bot::{GlobalState, SharedBot};
+use crate::util::log;
+use crate::util::tally::format_number;
+
+struct BotLine {
+    name: String,
+    enabled: bool,
+}
+
+/**
+Run the dashboard on the current terminal until `q` is pressed. Logs stop
+being printed while it runs since they would draw over it, they show up in
+the dashboard instead.
+*/
+pub async fn run(state: GlobalState, bots: Vec<SharedBot>) -> io::Result<()> {
+    log::set_quiet(true);
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run_loop(&mut terminal, &state, &bots).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    log::set_quiet(false);
+    result
+}
+
+async fn run_loop<B: Backend>(terminal: &mut Terminal<B>, state: &GlobalState, bots: &[SharedBot]) -> io::Result<()> {
+    let mut selected = 0;
+    let mut it = interval(Duration::from_millis(250));
+    loop {
+        it.tick().await;
+
+        let mut lines = Vec::new();
+        for bot in bots {
+            let mut bot = bot.lock().await;
+            lines.push(BotLine { name: bot.name().to_owned(), enabled: bot.is_enabled() });
+        }
+        terminal.draw(|f| draw(f, state, &lines, selected))?;
+
+        // Handle every key pressed since the last frame
+        while event::poll(Duration::from_millis(0))? {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(bots.len().saturating_sub(1)),
+                    KeyCode::Char('t') => {
+                        if let Some(bot) = bots.get(selected) {
+                            let mut bot = bot.lock().await;
+                            let enabled = bot.is_enabled();
+                            bot.set_enabled(!enabled);
+                        }
+                    },
+                    KeyCode::Char('s') => {
+                        if let Some(bot) = bots.get(selected) {
+                            bot.lock().await.start_poll(state).await;
+                        }
+                    },
+                    KeyCode::Char('d') => {
+                        if let Some(queued) = state.queue.pop() {
+                            log::info(format!("[Dashboard] Dropped {:?} from the queue", queued.question));
+                        }
+                    },
+                    KeyCode::Char('c') => {
+                        state.queue.clear();
+                    },
+                    KeyCode::Char('x') => {
+                        if let Some(bot) = bots.get(selected) {
+                            bot.lock().await.stop_poll(state).await;
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn draw<B: Backend>(f: &mut tui::Frame<B>, state: &GlobalState, bots: &[BotLine], selected: usize) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(8), Constraint::Length(10), Constraint::Length(1)])
+        .split(f.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25), Constraint::Percentage(40), Constraint::Percentage(35)])
+        .split(rows[1]);
+
+    // Status line
+    let mut status = vec![Span::raw([state.bot_name.clone(), " in #".to_owned(), state.channel_name.clone()].concat())];
+    for (name, value) in state.display.statuses() {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(name, Style::default().add_modifier(Modifier::BOLD)));
+        status.push(Span::raw([": ".to_owned(), value].concat()));
+    }
+    status.push(Span::raw(" | "));
+    status.push(Span::styled("Outbox", Style::default().add_modifier(Modifier::BOLD)));
+    status.push(Span::raw([": ".to_owned(), state.outbox.depth().to_string(), " queued".to_owned()].concat()));
+    if let Some(owner) = state.numbers.owner() {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled("Numbers", Style::default().add_modifier(Modifier::BOLD)));
+        status.push(Span::raw([": ".to_owned(), owner].concat()));
+    }
+    f.render_widget(
+        Paragraph::new(Spans::from(status)).block(Block::default().borders(Borders::ALL).title("Hivemind")),
+        rows[0],
+    );
+
+    // Bots
+    let items: Vec<ListItem> = bots.iter().enumerate().map(|(i, bot)| {
+        let style = if i == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        let (label, color) = if bot.enabled { ("ON ", Color::Green) } else { ("OFF", Color::Red) };
+        ListItem::new(Spans::from(vec![
+            Span::styled(label, Style::default().fg(color)),
+            Span::raw(" "),
+            Span::raw(bot.name.clone()),
+        ])).style(style)
+    }).collect();
+    f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title("Bots")), columns[0]);
+
+    // Polls, with the queued ones under them
+    let queue = state.queue.list();
+    let poll_rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(queue.len().clamp(1, 5) as u16 + 2)])
+        .split(columns[1]);
+    draw_polls(f, &state.display.polls(), poll_rows[0]);
+    let queued: Vec<ListItem> = queue.iter().enumerate()
+        .map(|(i, queued)| ListItem::new([
+            (i + 1).to_string(), ". ".to_owned(), queued.question.clone(), " (".to_owned(), queued.added_by.clone(), ")".to_owned()
+        ].concat()))
+        .collect();
+    f.render_widget(List::new(queued).block(Block::default().borders(Borders::ALL).title("Queue")), poll_rows[1]);
+
+    // Chat
+    f.render_widget(last_lines("Chat", state.display.recent_chat(), columns[2]), columns[2]);
+
+    // Logs, errors in red
+    let logs = log::recent();
+    let skip = logs.len().saturating_sub(rows[2].height.saturating_sub(2) as usize);
+    let logs: Vec<ListItem> = logs.into_iter().skip(skip)
+        .map(|line| {
+            let style = if line.is_error { Style::default().fg(Color::Red) } else { Style::default() };
+            let time = Local.timestamp_millis(line.timestamp).format("%H:%M:%S");
+            ListItem::new([time.to_string(), " ".to_owned(), line.text.replace('\n', " ")].concat()).style(style)
+        })
+        .collect();
+    f.render_widget(List::new(logs).block(Block::default().borders(Borders::ALL).title("Log")), rows[2]);
+
+    f.render_widget(
+        Paragraph::new("Up/Down select bot | s start poll | x stop poll | t toggle bot | d drop next queued | c clear queue | q quit"),
+        rows[3],
+    );
+}
+
+fn draw_polls<B: Backend>(f: &mut tui::Frame<B>, polls: &[PollView], area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Polls");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let now = Local::now().timestamp_millis();
+    let mut y = inner.y;
+    for poll in polls {
+        if y >= inner.y + inner.height {
+            break;
+        }
+        let mut title = [poll.bot.clone(), ": ".to_owned(), poll.question.clone()].concat();
+        if poll.is_open {
+            if let Some(closes_at) = poll.closes_at {
+                let left = ((closes_at - now).max(0) + 999) / 1000;
+                title = [title, " (".to_owned(), left.to_string(), "s left)".to_owned()].concat();
+            } else {
+                title = [title, " (open)".to_owned()].concat();
+            }
+        } else if let Some(winner) = &poll.winner {
+            title = [title, " - Winner: ".to_owned(), winner.clone()].concat();
+        } else if let Some(mean) = poll.rating.as_ref().and_then(|rating| rating.mean) {
+            title = [title, " - Mean: ".to_owned(), format_number(mean)].concat();
+        } else {
+            title = [title, " (closed)".to_owned()].concat();
+        }
+        f.render_widget(Paragraph::new(title), Rect { y, height: 1, ..inner });
+        y += 1;
+
+        if let Some(voters) = poll.secret_voters {
+            let labels: Vec<String> = poll.options.iter().map(|option| option.label.clone()).collect();
+            let line = [labels.join(", "), " - ".to_owned(), voters.to_string(), " voted, secret until it closes".to_owned()].concat();
+            f.render_widget(Paragraph::new(line), Rect { y, height: 1, ..inner });
+            y += 2;
+            continue;
+        }
+
+        let total: i32 = poll.options.iter().map(|o| o.votes).sum();
+        for option in &poll.options {
+            if y >= inner.y + inner.height {
+                break;
+            }
+            let ratio = if total > 0 { option.votes as f64 / total as f64 } else { 0.0 };
+            f.render_widget(
+                Gauge::default()
+                    .gauge_style(Style::default().fg(Color::Magenta))
+                    .ratio(ratio)
+                    .label(option_label(option)),
+                Rect { y, height: 1, ..inner },
+            );
+            y += 1;
+        }
+        y += 1;
+    }
+}
+
+/** `Mirage 14`, with the amount of voters if the votes were weighted */
+fn option_label(option: &OptionView) -> String {
+    let mut label = [option.label.clone(), " ".to_owned(), option.votes.to_string()].concat();
+    if option.voters != option.votes {
+        label = [label, " (".to_owned(), option.voters.to_string(), " voters)".to_owned()].concat();
+    }
+    label
+}
+
+/** List showing only as many of the latest lines as fit in `area` */
+fn last_lines<'a>(title: &'a str, lines: Vec<String>, area: Rect) -> List<'a> {
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = lines.len().saturating_sub(visible);
+    let items: Vec<ListItem> = lines.into_iter().skip(skip).map(ListItem::new).collect();
+    List::new(items).block(Block::default().borders(Borders::ALL).title(title))
+}
@@ -11,17 +11,40 @@ use twitch_irc::login::StaticLoginCredentials;
 use twitch_irc::message::ServerMessage;
 
 mod bots;
+mod display;
 mod util;
 
-use crate::util::bot::{Bot, Config, GlobalState};
-use crate::bots::{league_bot, vote_bot};
+use crate::util::bot::{self, Config, GlobalState, Handled, SharedBot};
+use crate::util::{audit::{self, AuditLog}, command, helix::Helix, history::{self, History}, log, metrics, lease::NumberLease, outbox::Outbox, queue::PollQueue};
+use crate::bots::{help_bot, league_bot, vote_bot};
+use crate::display::{Display, overlay::{self, Admin}, tui};
 
 #[tokio::main]
 pub async fn main() {
+    // `hivemind commands` prints the command reference and leaves
+    if std::env::args().nth(1).as_deref() == Some("commands") {
+        print!("{}", command::to_markdown(bots::REGISTRY));
+        return;
+    }
+
+    // `hivemind history csv` or `hivemind history json` prints every finished poll and leaves
+    if std::env::args().nth(1).as_deref() == Some("history") {
+        let records = History::new(history::HISTORY_PATH).load();
+        match std::env::args().nth(2).as_deref() {
+            Some("csv") => print!("{}", history::to_csv(&records)),
+            Some("json") => println!("{}", history::to_json(&records)),
+            _ => println!("Usage: hivemind history <csv|json>"),
+        }
+        return;
+    }
+
+    // `hivemind --tui` runs the dashboard instead of printing logs
+    let tui_mode = std::env::args().any(|arg| arg == "--tui");
+
     let bot_config: Config;
-    match tokio::fs::read_to_string("./config.toml").await {
-        Ok(bot_config_file) => {
-            bot_config = toml::from_str(&bot_config_file).unwrap();
+    match bot::load_config().await {
+        Ok(config) => {
+            bot_config = config;
         },
         Err(_) => panic!("No config provided or format is not compliant.")
     };
@@ -30,22 +53,47 @@ pub async fn main() {
     let bot_name = bot_config.bot_name.to_owned();
     let channel_name = bot_config.channel_name.to_owned();
 
-    let state = GlobalState { 
-        bot_name: bot_name.clone(),
-        channel_name: channel_name.clone()
-    };
-
     let config = ClientConfig::new_simple(
-        StaticLoginCredentials::new(state.bot_name.clone(), Some(oauth_token))
+        StaticLoginCredentials::new(bot_name.clone(), Some(oauth_token))
     );
 
     let (mut incoming_messages, client) =
         TwitchIRCClient::<SecureTCPTransport, StaticLoginCredentials>::new(config);
 
-    // Create the bots
-    // Maybe there's a better way to store pointers like this?
-    let vote_bot_box = Arc::new(Mutex::new(vote_bot::VoteBot::default()));
-    let league_bot_box = Arc::new(Mutex::new(league_bot::LeagueBot::default()));
+    let state = GlobalState { 
+        bot_name: bot_name.clone(),
+        channel_name: channel_name.clone(),
+        display: Display::default(),
+        outbox: Outbox::start(client.clone(), channel_name.clone()),
+        helix: Helix::new(bot_config.oauth_token.clone(), bot_config.client_id.clone()),
+        history: History::new(history::HISTORY_PATH),
+        audit: AuditLog::new(audit::AUDIT_PATH),
+        queue: PollQueue::default(),
+        numbers: NumberLease::default(),
+    };
+
+    // Serve the OBS overlay and the admin API if it was configured
+    if let Some(port) = bot_config.overlay_port {
+        let admin = Admin {
+            queue: state.queue.clone(),
+            presets: bot_config.vote_bot.presets.clone(),
+            token: bot_config.admin_token.clone(),
+            port,
+        };
+        tokio::spawn(overlay::serve(state.display.clone(), admin));
+    }
+
+    // Same for the metrics endpoint
+    if let Some(port) = bot_config.metrics_port {
+        tokio::spawn(metrics::serve(port));
+    }
+
+    // Create the bots, every thread gets a pointer to all of them
+    let bots: Vec<SharedBot> = vec![
+        Arc::new(Mutex::new(help_bot::HelpBot::default())),
+        Arc::new(Mutex::new(vote_bot::VoteBot::new(bot_config.vote_bot.clone()))),
+        Arc::new(Mutex::new(league_bot::LeagueBot::new(bot_config.league_bot.clone()))),
+    ];
 
     let (tx, mut rx) = mpsc::channel(100);
 
@@ -58,16 +106,23 @@ pub async fn main() {
             //println!("Received message: {:?}", message);
             match message {
                 ServerMessage::Privmsg(msg) => {
+                    metrics::MESSAGES_RECEIVED.with_label_values(&[&msg.channel_login]).inc();
+                    thread_state.display.push_chat(
+                        [msg.sender.name.clone(), ": ".to_owned(), msg.message_text.clone()].concat()
+                    );
                     tx.send(msg.clone()).await.unwrap();
                 },
                 //ServerMessage::ClearChat(_) => todo!(),
                 //ServerMessage::ClearMsg(_) => todo!(),
                 //ServerMessage::GlobalUserState(_) => todo!(),
                 //ServerMessage::HostTarget(_) => todo!(),
-                //ServerMessage::Join(_) => todo!(),
+                ServerMessage::Join(msg) => {
+                    thread_state.display.set_status("Twitch", ["Joined #".to_owned(), msg.channel_login].concat());
+                },
                 ServerMessage::Notice(msg) => {
-                    println!("Recieved notice: {:?}", msg.message_text);
+                    log::info(format!("Recieved notice: {:?}", msg.message_text));
                     if msg.message_text == "Login authentication failed" {
+                        thread_state.display.set_status("Twitch", "Login failed".to_owned());
                         thread_client.part(thread_state.channel_name.clone());
                         incoming_messages.close();
                     }
@@ -75,7 +130,10 @@ pub async fn main() {
                 //ServerMessage::Part(_) => todo!(),
                 //ServerMessage::Ping(_) => todo!(),
                 //ServerMessage::Pong(_) => todo!(),
-                //ServerMessage::Reconnect(_) => todo!(),
+                ServerMessage::Reconnect(_) => {
+                    metrics::RECONNECTS.with_label_values(&["twitch"]).inc();
+                    thread_state.display.set_status("Twitch", "Reconnecting".to_owned());
+                },
                 //ServerMessage::RoomState(_) => todo!(),
                 //ServerMessage::UserNotice(_) => todo!(),
                 //ServerMessage::UserState(_) => todo!(),
@@ -86,38 +144,69 @@ pub async fn main() {
         }
     });
 
-    // Second thread with bot message handling
-    let thread_client = client.clone();
+    // Second thread with bot message handling, highest priority first
     let thread_state = state.clone();
-    let vb_arc = vote_bot_box.clone();
-    let lb_arc = league_bot_box.clone();
+    let ignore = bot_config.ignore.clone();
+    let mut thread_bots = Vec::new();
+    for bot in &bots {
+        thread_bots.push((bot.lock().await.priority(), bot.clone()));
+    }
+    thread_bots.sort_by_key(|(priority, _)| -priority);
     let message_handler_handle = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
-            // Upstream messages to bots
-            vb_arc.lock().await.handle_message(&thread_state, &thread_client, &msg).await;
-            lb_arc.lock().await.handle_message(&thread_state, &thread_client, &msg).await;
+            // The bot itself and other chat bots could look like votes
+            if ignore.ignores(&thread_state.bot_name, &msg) {
+                metrics::MESSAGES_IGNORED.inc();
+                continue;
+            }
+            for (_, bot) in &thread_bots {
+                let mut bot = bot.lock().await;
+                if bot.is_enabled() {
+                    bot.observe(&msg);
+                }
+            }
+            // Upstream messages to bots until one of them uses it up
+            for (_, bot) in &thread_bots {
+                let mut bot = bot.lock().await;
+                if bot.is_enabled() && bot.handle_message(&thread_state, &msg).await == Handled::Consumed {
+                    break;
+                }
+            }
         }
     });
 
     // Third thread with bot updating every 2 seconds
-    let thread_client = client.clone();
     let thread_state = state.clone();
-    let vb_arc = vote_bot_box.clone();
-    let lb_arc = league_bot_box.clone();
+    let thread_bots = bots.clone();
     let updater_handle = tokio::spawn(async move {
         let mut it = interval(Duration::from_secs(1));
         // Update loop, waits for the tick
         loop {
             it.tick().await;
             // Update bots
-            vb_arc.lock().await.update(&thread_state, &thread_client).await;
-            lb_arc.lock().await.update(&thread_state, &thread_client).await;
+            for bot in &thread_bots {
+                let mut bot = bot.lock().await;
+                if bot.is_enabled() {
+                    bot.update(&thread_state).await;
+                } else {
+                    // A disabled bot's poll can't be holding on to the numbers
+                    thread_state.numbers.release(bot.name());
+                }
+            }
         }
     });
 
     // join a channel
     client.join(channel_name.clone().to_owned());
 
+    // The dashboard takes over the terminal, quitting it quits the bot
+    if tui_mode {
+        if let Err(err) = tui::run(state.clone(), bots.clone()).await {
+            println!("Dashboard stopped\n{}", err);
+        }
+        return;
+    }
+
     // keep the tokio executor alive.
     // If you return instead of waiting the background task will exit.
     join_handle.await.unwrap();
@@ -0,0 +1,29 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use crate::util::log;
+
+/** Where mod interventions on poll results are written down */
+pub const AUDIT_PATH: &str = "./audit.log";
+
+/** Append-only record of every veto and override, one line each */
+#[derive(Clone)]
+pub struct AuditLog {
+    pub path: String,
+}
+
+impl AuditLog {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_owned() }
+    }
+    /** Write down what someone did, like `[VoteBot] somemod overrode Dust with Mirage` */
+    pub fn record(&self, bot: &str, by: &str, action: String) {
+        let now = chrono::offset::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let line = format!("{} [{}] {} {}", now, bot, by, action);
+        log::info(["[Audit] ".to_owned(), line.clone()].concat());
+        let written = OpenOptions::new().create(true).append(true).open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(err) = written {
+            log::error(format!("[Audit] Couldn't write to {}\n{}", self.path, err));
+        }
+    }
+}
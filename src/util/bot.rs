@@ -1,14 +1,32 @@
-use twitch_irc::SecureTCPTransport;
-use twitch_irc::TwitchIRCClient;
-use twitch_irc::login::StaticLoginCredentials;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use twitch_irc::message::PrivmsgMessage;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use crate::bots::league_bot::LeagueBotConfig;
+use crate::bots::vote_bot::VoteBotConfig;
+use crate::display::Display;
+use crate::util::audit::AuditLog;
+use crate::util::helix::Helix;
+use crate::util::history::History;
+use crate::util::ignore::IgnoreRules;
+use crate::util::lease::NumberLease;
+use crate::util::outbox::Outbox;
+use crate::util::queue::PollQueue;
+
+pub const CONFIG_PATH: &str = "./config.toml";
 
 #[derive(Clone)]
 pub struct GlobalState {
     pub bot_name: String,
     pub channel_name: String,
+    pub display: Display,
+    pub outbox: Outbox,
+    pub helix: Helix,
+    pub history: History,
+    pub audit: AuditLog,
+    pub queue: PollQueue,
+    pub numbers: NumberLease,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -16,11 +34,59 @@ pub struct Config {
     pub oauth_token: String,
     pub bot_name: String,
     pub channel_name: String,
+    /** Client id of the app the token belongs to, needed to check followers */
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /** Port of the OBS overlay server and the admin API, both are disabled if not set */
+    #[serde(default)]
+    pub overlay_port: Option<u16>,
+    /** Port of the Prometheus `/metrics` endpoint, disabled if not set */
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    /** Token the admin API wants in `Authorization: Bearer <token>`, the API is off if not set */
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    #[serde(default)]
+    pub ignore: IgnoreRules,
+    #[serde(default)]
+    pub vote_bot: VoteBotConfig,
+    #[serde(default)]
+    pub league_bot: LeagueBotConfig,
+}
+
+/** Read and parse the config file */
+pub async fn load_config() -> Result<Config, String> {
+    let text = tokio::fs::read_to_string(CONFIG_PATH).await.map_err(|err| err.to_string())?;
+    toml::from_str(&text).map_err(|err| err.to_string())
+}
+
+/** What a bot did with a chat message */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Handled {
+    /** The next bots get to see it too */
+    Passed,
+    /** It was one of the bot's commands or a vote on its poll, the next bots don't see it */
+    Consumed,
 }
 
 #[async_trait]
 pub trait Bot {
+    fn name(&self) -> &str;
+    /** Bots with a higher priority get chat messages first and can keep them from the others */
+    fn priority(&self) -> i32 {
+        0
+    }
     fn is_enabled(&mut self) -> bool;
-    async fn handle_message(&mut self, global_state: &GlobalState, client: &TwitchIRCClient<SecureTCPTransport, StaticLoginCredentials>, msg: &PrivmsgMessage);
-    async fn update(&mut self, global_state: &GlobalState, client: &TwitchIRCClient<SecureTCPTransport, StaticLoginCredentials>);
-}
\ No newline at end of file
+    fn set_enabled(&mut self, enabled: bool);
+    /** Sees every chat message before any bot handles it, even the ones another bot consumes */
+    fn observe(&mut self, _msg: &PrivmsgMessage) {}
+    async fn handle_message(&mut self, global_state: &GlobalState, msg: &PrivmsgMessage) -> Handled;
+    async fn update(&mut self, global_state: &GlobalState);
+    /** Open a poll without anyone typing the command in chat */
+    async fn start_poll(&mut self, global_state: &GlobalState);
+    /** Close the running poll without anyone typing the command in chat */
+    async fn stop_poll(&mut self, global_state: &GlobalState);
+}
+
+/** A bot shared between the message handler, the updater and the dashboard */
+pub type SharedBot = Arc<Mutex<dyn Bot + Send>>;
\ No newline at end of file
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::util::twitch::{has_badge, is_mod};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Permission {
+    Everyone,
+    /** Moderators and the broadcaster */
+    Moderator,
+    /** Only the streamer */
+    Broadcaster,
+}
+
+/** A chat command a bot answers to, this is what the help is generated from */
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub permission: Permission,
+    /** Seconds before the command can be used again */
+    pub cooldown: Option<i64>,
+}
+
+impl Command {
+    /** Returns true if the text is this command's name or one of its aliases */
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let text = text.strip_prefix('!').unwrap_or(&text);
+        self.name.trim_start_matches('!') == text
+            || self.aliases.iter().any(|alias| alias.trim_start_matches('!') == text)
+    }
+    /** Returns true if the sender of the message is allowed to run this */
+    pub fn is_allowed(&self, msg: &PrivmsgMessage) -> bool {
+        match self.permission {
+            Permission::Everyone => true,
+            Permission::Moderator => is_mod(msg),
+            Permission::Broadcaster => has_badge(msg, "broadcaster"),
+        }
+    }
+    /** One line description for chat */
+    pub fn help_line(&self) -> String {
+        let mut strings = vec![self.usage.to_owned(), " - ".to_owned(), self.description.to_owned()];
+        if !self.aliases.is_empty() {
+            strings.push(" Aliases: ".to_owned());
+            strings.push(self.aliases.join(", "));
+            strings.push(".".to_owned());
+        }
+        match self.permission {
+            Permission::Moderator => strings.push(" Mods only.".to_owned()),
+            Permission::Broadcaster => strings.push(" Streamer only.".to_owned()),
+            Permission::Everyone => {},
+        }
+        if let Some(cooldown) = self.cooldown {
+            strings.push([" Cooldown: ".to_owned(), cooldown.to_string(), "s.".to_owned()].concat());
+        }
+        strings.concat()
+    }
+}
+
+/**
+Find the command a message is calling. Only the first word of the message is
+looked at and the command is only returned if the sender is allowed to run it.
+*/
+pub fn find<'a>(commands: &'a [Command], msg: &PrivmsgMessage) -> Option<&'a Command> {
+    let first = msg.message_text.split_whitespace().next()?;
+    if !first.starts_with('!') {
+        return None;
+    }
+    commands.iter().find(|command| command.matches(first) && command.is_allowed(msg))
+}
+
+/** Everything after the command's name */
+pub fn args(msg: &PrivmsgMessage) -> Vec<&str> {
+    msg.message_text.split_whitespace().skip(1).collect()
+}
+
+/** Longest duration `parse_duration` takes, a day is plenty for a stream */
+const MAX_DURATION: i64 = 24 * 3600;
+
+/**
+Parse a duration like `90`, `60s`, `2m` or `1m30s` into seconds. Zero and
+anything longer than `MAX_DURATION` are `None`, so callers can turn it into
+ms without overflowing.
+*/
+pub fn parse_duration(text: &str) -> Option<i64> {
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for c in text.trim().to_lowercase().chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            's' | 'm' | 'h' => {
+                let value: i64 = number.parse().ok()?;
+                let seconds = match c {
+                    's' => Some(value),
+                    'm' => value.checked_mul(60),
+                    _ => value.checked_mul(3600),
+                };
+                total = total.checked_add(seconds?)?;
+                number.clear();
+            },
+            _ => return None,
+        }
+    }
+    // A number without a unit is seconds
+    if !number.is_empty() {
+        total = total.checked_add(number.parse::<i64>().ok()?)?;
+    }
+    if total == 0 || total > MAX_DURATION {
+        return None;
+    }
+    Some(total)
+}
+
+/** Keeps track of when each command was last used */
+#[derive(Default)]
+pub struct Cooldowns {
+    last_used: HashMap<&'static str, i64>,
+}
+
+impl Cooldowns {
+    /** Returns true and restarts the cooldown if the command can be used now */
+    pub fn ready(&mut self, command: &Command) -> bool {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        if let (Some(cooldown), Some(last_used)) = (command.cooldown, self.last_used.get(command.name)) {
+            if now - last_used < cooldown * 1000 {
+                return false;
+            }
+        }
+        self.last_used.insert(command.name, now);
+        true
+    }
+}
+
+/** Markdown reference of every command, grouped by bot */
+pub fn to_markdown(registry: &[(&str, &[Command])]) -> String {
+    let mut strings = vec!["# Commands\n".to_owned()];
+    for (bot, commands) in registry {
+        strings.push(["\n## ".to_owned(), bot.to_string(), "\n\n".to_owned()].concat());
+        strings.push("| Command | Aliases | Who | Cooldown | Description |\n".to_owned());
+        strings.push("| ------- | ------- | --- | -------- | ----------- |\n".to_owned());
+        for command in commands.iter() {
+            let aliases = command.aliases.iter()
+                .map(|alias| ["`".to_owned(), alias.to_string(), "`".to_owned()].concat())
+                .collect::<Vec<String>>()
+                .join(", ");
+            let who = match command.permission {
+                Permission::Everyone => "Everyone",
+                Permission::Moderator => "Mods",
+                Permission::Broadcaster => "Streamer",
+            };
+            let cooldown = command.cooldown
+                .map(|cooldown| [cooldown.to_string(), "s".to_owned()].concat())
+                .unwrap_or_default();
+            strings.push([
+                "| `".to_owned(), command.usage.replace('|', "\\|"), "` | ".to_owned(),
+                aliases, " | ".to_owned(),
+                who.to_owned(), " | ".to_owned(),
+                cooldown, " | ".to_owned(),
+                command.description.to_owned(), " |\n".to_owned(),
+            ].concat());
+        }
+    }
+    strings.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_with_and_without_units() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("60s"), Some(60));
+        assert_eq!(parse_duration("2m"), Some(120));
+        assert_eq!(parse_duration("1m30s"), Some(90));
+        assert_eq!(parse_duration(" 1H "), Some(3600));
+        assert_eq!(parse_duration("1m30"), Some(90));
+    }
+
+    #[test]
+    fn zero_is_no_duration() {
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("0m0s"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn durations_that_would_overflow_are_rejected() {
+        assert_eq!(parse_duration("9223372036854775807h"), None);
+        assert_eq!(parse_duration("9223372036854775807s1s"), None);
+        assert_eq!(parse_duration("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn durations_stop_at_a_day() {
+        assert_eq!(parse_duration("24h"), Some(MAX_DURATION));
+        assert_eq!(parse_duration("24h1s"), None);
+    }
+
+    #[test]
+    fn anything_else_is_no_duration() {
+        assert_eq!(parse_duration("1x"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("-5"), None);
+    }
+}
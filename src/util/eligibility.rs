@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use twitch_irc::message::{PrivmsgMessage, TwitchUserBasics};
+use crate::util::helix::Helix;
+use crate::util::twitch::has_badge;
+
+/** Who gets to vote, bots have a set in their config and polls can add to it */
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EligibilityRules {
+    pub followers_only: bool,
+    pub subscribers_only: bool,
+    /** Only chatters the bot saw talking before the poll opened */
+    pub chatted_before: bool,
+    /** Not a chatter's first message ever in the channel */
+    pub no_first_message: bool,
+    /** Logins that can't vote */
+    pub deny_list: Vec<String>,
+}
+
+impl EligibilityRules {
+    /** Turn on a rule by the name polls use, like `subs`, returns false if there's none */
+    pub fn enable(&mut self, name: &str) -> bool {
+        match name.to_lowercase().as_str() {
+            "followers" => self.followers_only = true,
+            "subs" | "subscribers" => self.subscribers_only = true,
+            "chatters" => self.chatted_before = true,
+            "returning" => self.no_first_message = true,
+            _ => return false,
+        }
+        true
+    }
+    /** Every rule of both */
+    pub fn and(&self, other: &EligibilityRules) -> EligibilityRules {
+        EligibilityRules {
+            followers_only: self.followers_only || other.followers_only,
+            subscribers_only: self.subscribers_only || other.subscribers_only,
+            chatted_before: self.chatted_before || other.chatted_before,
+            no_first_message: self.no_first_message || other.no_first_message,
+            deny_list: self.deny_list.iter()
+                .chain(other.deny_list.iter().filter(|login| !self.deny_list.contains(login)))
+                .cloned()
+                .collect(),
+        }
+    }
+    /** Like `Only subscribers and followers can vote`, `None` when everyone can */
+    pub fn summary(&self) -> Option<String> {
+        let mut who = Vec::new();
+        if self.subscribers_only {
+            who.push("subscribers");
+        }
+        if self.followers_only {
+            who.push("followers");
+        }
+        if self.chatted_before {
+            who.push("people who chatted before the poll");
+        }
+        if self.no_first_message {
+            who.push("returning chatters");
+        }
+        if who.is_empty() {
+            return None;
+        }
+        Some(["Only ".to_owned(), who.join(" and "), " can vote".to_owned()].concat())
+    }
+}
+
+/** Why a vote didn't count */
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rejection {
+    Denied,
+    NotFollower,
+    NotSubscriber,
+    NewChatter,
+    FirstMessage,
+}
+
+impl Rejection {
+    const ALL: [Rejection; 5] = [
+        Rejection::Denied,
+        Rejection::NotFollower,
+        Rejection::NotSubscriber,
+        Rejection::NewChatter,
+        Rejection::FirstMessage,
+    ];
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Rejection::Denied => "denied",
+            Rejection::NotFollower => "not following",
+            Rejection::NotSubscriber => "not subscribed",
+            Rejection::NewChatter => "new to chat",
+            Rejection::FirstMessage => "first message",
+        }
+    }
+}
+
+/** Keeps track of who's been chatting, to check voters against the rules */
+#[derive(Default)]
+pub struct Eligibility {
+    /** Chatter id -> when the bot first saw them talk */
+    first_seen: HashMap<String, i64>,
+}
+
+impl Eligibility {
+    /** Call on every message, before checking it */
+    pub fn saw(&mut self, msg: &PrivmsgMessage) {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        self.first_seen.entry(msg.sender.id.clone()).or_insert(now);
+    }
+    /**
+    Check the sender of a vote against the rules of a poll opened at
+    `opened_at`. It's `None` while Twitch is being asked if they follow, the
+    vote has to be checked again in a bit.
+    */
+    pub fn check(&self, rules: &EligibilityRules, msg: &PrivmsgMessage, opened_at: i64, helix: &Helix) -> Option<Result<(), Rejection>> {
+        if rules.deny_list.iter().any(|login| login.eq_ignore_ascii_case(&msg.sender.login)) {
+            return Some(Err(Rejection::Denied));
+        }
+        if rules.no_first_message && msg.source.tags.0.get("first-msg") == Some(&Some("1".to_owned())) {
+            return Some(Err(Rejection::FirstMessage));
+        }
+        if rules.chatted_before && !matches!(self.first_seen.get(&msg.sender.id), Some(&at) if at < opened_at) {
+            return Some(Err(Rejection::NewChatter));
+        }
+        // The broadcaster counts as both
+        let is_broadcaster = has_badge(msg, "broadcaster");
+        if rules.subscribers_only && !is_broadcaster && !has_badge(msg, "subscriber") && !has_badge(msg, "founder") {
+            return Some(Err(Rejection::NotSubscriber));
+        }
+        // Last since it's the only one asking Twitch
+        if rules.followers_only && !is_broadcaster && !helix.is_follower(&msg.channel_id, &msg.sender.id)? {
+            return Some(Err(Rejection::NotFollower));
+        }
+        Some(Ok(()))
+    }
+}
+
+/** Voter id -> why their vote didn't count, for one poll */
+#[derive(Default)]
+pub struct Rejections {
+    voters: HashMap<String, Rejection>,
+}
+
+impl Rejections {
+    /** Remember the rejection, returns true the first time a voter gets rejected */
+    pub fn add(&mut self, voter: &TwitchUserBasics, rejection: Rejection) -> bool {
+        self.voters.insert(voter.id.clone(), rejection).is_none()
+    }
+    /** Forget a voter's rejection, their vote got in after all */
+    pub fn remove(&mut self, voter: &TwitchUserBasics) {
+        self.voters.remove(&voter.id);
+    }
+    pub fn clear(&mut self) {
+        self.voters.clear();
+    }
+    /** Like `5 votes rejected: 3 not subscribed, 2 first message`, `None` if there's none */
+    pub fn summary(&self) -> Option<String> {
+        if self.voters.is_empty() {
+            return None;
+        }
+        let reasons: Vec<String> = Rejection::ALL.iter()
+            .map(|reason| (reason, self.voters.values().filter(|&rejection| rejection == reason).count()))
+            .filter(|(_, count)| *count > 0)
+            .map(|(reason, count)| [count.to_string(), " ".to_owned(), reason.describe().to_owned()].concat())
+            .collect();
+        Some([
+            self.voters.len().to_string(),
+            if self.voters.len() == 1 { " vote rejected: ".to_owned() } else { " votes rejected: ".to_owned() },
+            reasons.join(", ")
+        ].concat())
+    }
+}
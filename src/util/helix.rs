@@ -0,0 +1,106 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serde::Deserialize;
+use crate::util::log;
+
+/** Not following gets asked again after this long, they might follow in the meantime */
+const NOT_FOLLOWING_TTL: i64 = 60 * 1000;
+
+/** Twitch gets this long to answer, not answering counts as not following */
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct FollowersResponse {
+    data: Vec<serde_json::Value>,
+}
+
+/**
+Just enough of the Twitch Helix API to check followers. It uses the bot's token
+so it needs the `moderator:read:followers` scope and the `client_id` the token
+was made with.
+*/
+#[derive(Clone)]
+pub struct Helix {
+    http: reqwest::Client,
+    token: String,
+    client_id: Option<String>,
+    /** User id -> (is following, when it was checked) */
+    followers: Arc<Mutex<HashMap<String, (bool, i64)>>>,
+    /** Users Twitch is being asked about right now */
+    asking: Arc<Mutex<HashSet<String>>>,
+    /** The missing `client_id` only gets logged once */
+    warned: Arc<AtomicBool>,
+}
+
+impl Helix {
+    pub fn new(token: String, client_id: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            token,
+            client_id,
+            followers: Default::default(),
+            asking: Default::default(),
+            warned: Default::default(),
+        }
+    }
+    /**
+    Returns true if the user follows the channel. This never waits on Twitch,
+    users it doesn't know about yet get looked up in the background and it's
+    `None` until the answer is in. Without a `client_id` nobody follows.
+    */
+    pub fn is_follower(&self, broadcaster_id: &str, user_id: &str) -> Option<bool> {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        if let Some(&(following, checked_at)) = self.followers.lock().unwrap().get(user_id) {
+            if following || now - checked_at < NOT_FOLLOWING_TTL {
+                return Some(following);
+            }
+        }
+        let client_id = match &self.client_id {
+            Some(client_id) => client_id.clone(),
+            None => {
+                if !self.warned.swap(true, Ordering::Relaxed) {
+                    log::error("[Helix] Checking followers needs a client_id in the config, nobody counts as following".to_owned());
+                }
+                return Some(false);
+            }
+        };
+        if !self.asking.lock().unwrap().insert(user_id.to_owned()) {
+            return None;
+        }
+        let helix = self.clone();
+        let (broadcaster_id, user_id) = (broadcaster_id.to_owned(), user_id.to_owned());
+        tokio::spawn(async move {
+            // Not knowing doesn't count as following, it gets asked again once it expires
+            let following = helix.ask(&broadcaster_id, &user_id, &client_id).await.unwrap_or(false);
+            let now = chrono::offset::Local::now().timestamp_millis();
+            helix.followers.lock().unwrap().insert(user_id.clone(), (following, now));
+            helix.asking.lock().unwrap().remove(&user_id);
+        });
+        None
+    }
+    /** Ask Twitch if the user follows the channel, `None` if it couldn't tell */
+    async fn ask(&self, broadcaster_id: &str, user_id: &str, client_id: &str) -> Option<bool> {
+        let response = self.http.get("https://api.twitch.tv/helix/channels/followers")
+            .query(&[("broadcaster_id", broadcaster_id), ("user_id", user_id)])
+            .bearer_auth(&self.token)
+            .header("Client-Id", client_id)
+            .timeout(LOOKUP_TIMEOUT)
+            .send().await
+            .and_then(|response| response.error_for_status());
+        match response {
+            Ok(response) => match response.json::<FollowersResponse>().await {
+                Ok(followers) => Some(!followers.data.is_empty()),
+                Err(e) => {
+                    log::error(format!("[Helix] Weird followers response: {}", e));
+                    None
+                }
+            },
+            Err(e) => {
+                log::error(format!("[Helix] Couldn't check followers: {}", e));
+                None
+            }
+        }
+    }
+}
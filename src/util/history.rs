@@ -0,0 +1,265 @@
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Write;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use crate::util::log;
+use crate::util::poll::{Choice, Poll};
+
+/** Where finished polls are kept, one JSON object per line */
+pub const HISTORY_PATH: &str = "./poll_history.jsonl";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OptionRecord {
+    pub label: String,
+    pub votes: i32,
+    pub voters: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BallotRecord {
+    pub voter_id: String,
+    pub voter_name: String,
+    /** Labels of the options, in the order the voter gave them */
+    pub choices: Vec<String>,
+    pub amount: i32,
+    pub rating: Option<f64>,
+}
+
+/** A finished poll, everything needed to look at it after the stream */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PollRecord {
+    pub bot: String,
+    pub question: String,
+    pub options: Vec<OptionRecord>,
+    pub ballots: Vec<BallotRecord>,
+    pub winner: Option<String>,
+    pub opened_at: i64,
+    pub closed_at: i64,
+    pub opened_by: String,
+    pub closed_by: String,
+    /** What mods did about the result, like `Dust vetoed by someone, voted again` */
+    #[serde(default)]
+    pub veto: Option<String>,
+}
+
+impl PollRecord {
+    /** Record a poll that just closed */
+    pub fn new<O: Choice>(poll: &Poll<O>, question: &str, winner: Option<String>) -> Self {
+        let mut ballots: Vec<BallotRecord> = poll.voters.iter()
+            .map(|(voter_id, ballot)| BallotRecord {
+                voter_id: voter_id.clone(),
+                voter_name: ballot.name.clone(),
+                choices: ballot.choices.iter()
+                    .filter_map(|&i| poll.options.get(i))
+                    .map(|option| option.label())
+                    .collect(),
+                amount: ballot.amount,
+                rating: ballot.rating,
+            })
+            .collect();
+        ballots.sort_by(|a, b| a.voter_name.cmp(&b.voter_name));
+        Self {
+            bot: poll.bot.to_owned(),
+            question: question.to_owned(),
+            options: poll.options.iter()
+                .map(|option| OptionRecord { label: option.label(), votes: option.votes, voters: option.voters })
+                .collect(),
+            ballots,
+            winner,
+            opened_at: poll.opened_at,
+            closed_at: poll.closed_at,
+            opened_by: poll.opened_by.clone(),
+            closed_by: poll.closed_by.clone(),
+            veto: None,
+        }
+    }
+    /** Like `Which map? -> Mirage` */
+    pub fn short(&self) -> String {
+        [
+            self.question.clone(),
+            " -> ".to_owned(),
+            self.winner.clone().unwrap_or_else(|| "no winner".to_owned())
+        ].concat()
+    }
+}
+
+/** Like `Which map? (VoteBot, 21:04) Winner: Mirage. Mirage 14, Dust 9` */
+impl Display for PollRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut strings = vec![
+            self.question.clone(),
+            " (".to_owned(),
+            self.bot.clone(),
+            ", ".to_owned(),
+            format_time(self.closed_at, "%H:%M"),
+            ")".to_owned()
+        ];
+        if let Some(winner) = &self.winner {
+            strings.push([" Winner: ".to_owned(), winner.clone(), ".".to_owned()].concat());
+        }
+        let tally: Vec<String> = self.options.iter()
+            .map(|option| [option.label.clone(), " ".to_owned(), option.votes.to_string()].concat())
+            .collect();
+        if tally.is_empty() {
+            strings.push([" ".to_owned(), self.ballots.len().to_string(), " votes".to_owned()].concat());
+        } else {
+            strings.push([" ".to_owned(), tally.join(", ")].concat());
+        }
+        write!(f, "{}", strings.concat())
+    }
+}
+
+/** Finished polls of every bot, kept in a file so they survive restarts */
+#[derive(Clone)]
+pub struct History {
+    pub path: String,
+}
+
+impl History {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_owned() }
+    }
+    /** Add a finished poll at the end of the file */
+    pub fn save(&self, record: &PollRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => return log::error(format!("[History] Couldn't serialize the poll\n{}", err)),
+        };
+        let written = OpenOptions::new().create(true).append(true).open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(err) = written {
+            log::error(format!("[History] Couldn't write to {}\n{}", self.path, err));
+        }
+    }
+    /**
+    Replace the saved poll with the same bot and opening time, or add it if it
+    isn't saved yet. For polls that only get decided after they closed.
+    */
+    pub fn update(&self, record: &PollRecord) {
+        let text = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let mut lines: Vec<String> = text.lines().map(|line| line.to_owned()).collect();
+        let saved = lines.iter().rposition(|line| matches!(
+            serde_json::from_str::<PollRecord>(line),
+            Ok(saved) if saved.bot == record.bot && saved.opened_at == record.opened_at
+        ));
+        let position = match saved {
+            Some(position) => position,
+            None => return self.save(record),
+        };
+        lines[position] = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => return log::error(format!("[History] Couldn't serialize the poll\n{}", err)),
+        };
+        lines.push(String::new());
+        if let Err(err) = std::fs::write(&self.path, lines.join("\n")) {
+            log::error(format!("[History] Couldn't write to {}\n{}", self.path, err));
+        }
+    }
+    /** Every finished poll, oldest first. Lines that don't parse are skipped */
+    pub fn load(&self) -> Vec<PollRecord> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => text.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+    /** The last `count` polls, newest first */
+    pub fn last(&self, count: usize) -> Vec<PollRecord> {
+        self.load().into_iter().rev().take(count).collect()
+    }
+}
+
+/** Every poll as a JSON array */
+pub fn to_json(records: &[PollRecord]) -> String {
+    serde_json::to_string_pretty(records).unwrap_or_default()
+}
+
+/** One row per option of every poll, ballots are only in the JSON */
+pub fn to_csv(records: &[PollRecord]) -> String {
+    let mut lines = vec![
+        "bot,question,opened_at,closed_at,opened_by,closed_by,winner,veto,option,votes,voters".to_owned()
+    ];
+    for record in records {
+        for option in &record.options {
+            let fields = [
+                record.bot.clone(),
+                record.question.clone(),
+                format_time(record.opened_at, "%Y-%m-%d %H:%M:%S"),
+                format_time(record.closed_at, "%Y-%m-%d %H:%M:%S"),
+                record.opened_by.clone(),
+                record.closed_by.clone(),
+                record.winner.clone().unwrap_or_default(),
+                record.veto.clone().unwrap_or_default(),
+                option.label.clone(),
+                option.votes.to_string(),
+                option.voters.to_string(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            lines.push(fields.join(","));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/** Quote a field if it needs it, quotes inside get doubled */
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        ["\"".to_owned(), field.replace('"', "\"\""), "\"".to_owned()].concat()
+    } else {
+        field.to_owned()
+    }
+}
+
+/** Local time of a timestamp in ms */
+fn format_time(timestamp: i64, format: &str) -> String {
+    chrono::Local.timestamp_millis(timestamp).format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(question: &str, labels: &[&str]) -> PollRecord {
+        PollRecord {
+            bot: "VoteBot".to_owned(),
+            question: question.to_owned(),
+            options: labels.iter()
+                .map(|label| OptionRecord { label: label.to_string(), votes: 1, voters: 1 })
+                .collect(),
+            ballots: Vec::new(),
+            winner: None,
+            opened_at: 0,
+            closed_at: 0,
+            opened_by: "someone".to_owned(),
+            closed_by: "timer".to_owned(),
+            veto: None,
+        }
+    }
+
+    #[test]
+    fn csv_fields_with_commas_and_quotes_get_quoted() {
+        assert_eq!(csv_field("Mirage"), "Mirage");
+        assert_eq!(csv_field("Dust, Mirage"), "\"Dust, Mirage\"");
+        assert_eq!(csv_field("The \"best\" map"), "\"The \"\"best\"\" map\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn csv_has_a_row_per_option() {
+        let csv = to_csv(&[record("Which map, \"really\"?", &["Dust", "Mirage, again"])]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "bot,question,opened_at,closed_at,opened_by,closed_by,winner,veto,option,votes,voters");
+        assert!(lines[1].starts_with("VoteBot,\"Which map, \"\"really\"\"?\","));
+        assert!(lines[1].ends_with(",someone,timer,,,Dust,1,1"));
+        assert!(lines[2].ends_with(",\"Mirage, again\",1,1"));
+    }
+
+    #[test]
+    fn csv_without_polls_is_only_the_header() {
+        assert_eq!(to_csv(&[]), "bot,question,opened_at,closed_at,opened_by,closed_by,winner,veto,option,votes,voters\n");
+    }
+}
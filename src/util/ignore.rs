@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use twitch_irc::message::PrivmsgMessage;
+
+/** Chat bots that show up in a lot of channels, `known_bots` ignores them */
+const KNOWN_BOTS: &[&str] = &[
+    "nightbot",
+    "streamelements",
+    "streamlabs",
+    "moobot",
+    "fossabot",
+    "wizebot",
+    "botisimo",
+    "deepbot",
+    "phantombot",
+    "coebot",
+    "sery_bot",
+    "soundalerts",
+    "streamstickers",
+    "kofistreambot",
+    "pokemoncommunitygame",
+    "commanderroot",
+];
+
+/** The `[ignore]` section of the config, chatters none of the bots listen to */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct IgnoreRules {
+    /** Logins to ignore, the bot's own `bot_name` always is */
+    pub users: Vec<String>,
+    /** Also ignore Nightbot, StreamElements and the other well known chat bots */
+    pub known_bots: bool,
+    /** Also ignore every login ending in `bot`, like `coolstreambot` */
+    pub bot_like_names: bool,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self {
+            users: Vec::new(),
+            known_bots: true,
+            bot_like_names: false,
+        }
+    }
+}
+
+impl IgnoreRules {
+    /** Returns true if no bot should see this message */
+    pub fn ignores(&self, bot_name: &str, msg: &PrivmsgMessage) -> bool {
+        let login = msg.sender.login.to_lowercase();
+        login == bot_name.to_lowercase()
+            || self.users.iter().any(|user| user.to_lowercase() == login)
+            || (self.known_bots && KNOWN_BOTS.contains(&login.as_str()))
+            || (self.bot_like_names && login.ends_with("bot"))
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::{Arc, Mutex};
+
+/**
+Which bot's poll gets the bare numbers like `1` in chat. Every poll has a
+first option, so only one poll at a time takes numbers and the others are
+voted on with their options' text until it closes.
+*/
+#[derive(Clone, Default)]
+pub struct NumberLease {
+    owner: Arc<Mutex<Option<String>>>,
+}
+
+impl NumberLease {
+    /**
+    Take the numbers if the bot has a poll open and nobody has them, give them
+    back once it doesn't. Returns true if the bot has them.
+    */
+    pub fn sync(&self, bot: &str, has_poll: bool) -> bool {
+        let mut owner = self.owner.lock().unwrap();
+        match owner.as_deref() {
+            None if has_poll => *owner = Some(bot.to_owned()),
+            Some(current) if current == bot && !has_poll => *owner = None,
+            _ => {},
+        }
+        owner.as_deref() == Some(bot)
+    }
+    /** Give the numbers back if the bot has them, like when it gets disabled */
+    pub fn release(&self, bot: &str) {
+        self.sync(bot, false);
+    }
+    pub fn owner(&self) -> Option<String> {
+        self.owner.lock().unwrap().clone()
+    }
+}
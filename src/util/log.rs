@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/** How many lines are kept around for the dashboard */
+const MAX_LINES: usize = 100;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static RECENT: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone)]
+pub struct LogLine {
+    pub timestamp: i64,
+    pub is_error: bool,
+    pub text: String,
+}
+
+/** Print a line and keep it for the dashboard */
+pub fn info(text: String) {
+    push(false, text);
+}
+
+/** Same as `info` but the dashboard shows it with the errors */
+pub fn error(text: String) {
+    push(true, text);
+}
+
+/** Stop printing to stdout, used when something else owns the terminal */
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/** The most recent lines, oldest first */
+pub fn recent() -> Vec<LogLine> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+fn push(is_error: bool, text: String) {
+    if !QUIET.load(Ordering::Relaxed) {
+        println!("{}", text);
+    }
+    let mut recent = RECENT.lock().unwrap();
+    if recent.len() >= MAX_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(LogLine {
+        timestamp: chrono::offset::Local::now().timestamp_millis(),
+        is_error,
+        text,
+    });
+}
@@ -0,0 +1,137 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use lazy_static::lazy_static;
+use prometheus::{Encoder, HistogramVec, IntCounter, IntCounterVec, TextEncoder};
+use prometheus::{register_histogram_vec, register_int_counter, register_int_counter_vec};
+
+use crate::util::log;
+use crate::util::poll::PollEvent;
+
+lazy_static! {
+    pub static ref MESSAGES_RECEIVED: IntCounterVec = register_int_counter_vec!(
+        "hivemind_messages_received_total",
+        "Chat messages received",
+        &["channel"]
+    ).unwrap();
+    pub static ref MESSAGES_IGNORED: IntCounter = register_int_counter!(
+        "hivemind_messages_ignored_total",
+        "Chat messages no bot saw because of the ignore list"
+    ).unwrap();
+    pub static ref COMMANDS_HANDLED: IntCounterVec = register_int_counter_vec!(
+        "hivemind_commands_handled_total",
+        "Chat commands handled",
+        &["bot", "command"]
+    ).unwrap();
+    pub static ref VOTES_CAST: IntCounterVec = register_int_counter_vec!(
+        "hivemind_votes_cast_total",
+        "Votes counted per option by its number, secret ballots are only counted once they close",
+        &["bot", "option"]
+    ).unwrap();
+    pub static ref VOTES_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "hivemind_votes_rejected_total",
+        "Voters whose votes didn't count, by the eligibility rule they broke",
+        &["bot", "reason"]
+    ).unwrap();
+    pub static ref VOTE_WINDOWS_OPENED: IntCounterVec = register_int_counter_vec!(
+        "hivemind_vote_windows_opened_total",
+        "Polls opened",
+        &["bot"]
+    ).unwrap();
+    pub static ref VOTE_WINDOWS_CLOSED: IntCounterVec = register_int_counter_vec!(
+        "hivemind_vote_windows_closed_total",
+        "Polls closed",
+        &["bot"]
+    ).unwrap();
+    pub static ref VOTE_WINDOW_DURATION: HistogramVec = register_histogram_vec!(
+        "hivemind_vote_window_duration_seconds",
+        "How long polls stayed open",
+        &["bot"],
+        vec![5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0]
+    ).unwrap();
+    pub static ref LEAGUE_POLL_LATENCY: HistogramVec = register_histogram_vec!(
+        "hivemind_league_poll_latency_seconds",
+        "Time taken by requests to the league client",
+        &["outcome"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    ).unwrap();
+    pub static ref LEAGUE_POLL_FAILURES: IntCounter = register_int_counter!(
+        "hivemind_league_poll_failures_total",
+        "Requests to the league client that failed"
+    ).unwrap();
+    pub static ref KEY_PRESSES: IntCounterVec = register_int_counter_vec!(
+        "hivemind_key_presses_total",
+        "Synthetic key presses sent to the game",
+        &["key"]
+    ).unwrap();
+    pub static ref OUTBOUND_QUEUED: IntCounter = register_int_counter!(
+        "hivemind_outbound_messages_queued_total",
+        "Messages queued to be sent to chat"
+    ).unwrap();
+    pub static ref OUTBOUND_DROPPED: IntCounter = register_int_counter!(
+        "hivemind_outbound_messages_dropped_total",
+        "Messages dropped because the outbox was full"
+    ).unwrap();
+    pub static ref RECONNECTS: IntCounterVec = register_int_counter_vec!(
+        "hivemind_reconnects_total",
+        "Reconnections to Twitch or the league client",
+        &["target"]
+    ).unwrap();
+}
+
+/** Serve the metrics in the Prometheus text format on `127.0.0.1:<port>/metrics` */
+pub async fn serve(port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(handle))
+    });
+    log::info(format!("[Metrics] Serving metrics on http://{}/metrics", addr));
+    if let Err(err) = Server::bind(&addr).serve(make_service).await {
+        log::error(format!("[Metrics] Server stopped\n{}", err));
+    }
+}
+
+async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let encoder = TextEncoder::new();
+            let mut buffer = Vec::new();
+            encoder.encode(&prometheus::gather(), &mut buffer).unwrap();
+            Response::builder()
+                .header("Content-Type", encoder.format_type())
+                .body(Body::from(buffer))
+        },
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    Ok(response.unwrap())
+}
+
+/** Count a command a bot acted on */
+pub fn command_handled(bot: &str, command: &str) {
+    COMMANDS_HANDLED.with_label_values(&[bot, command]).inc();
+}
+
+/** Count a synthetic key press */
+pub fn key_pressed(key: &str) {
+    KEY_PRESSES.with_label_values(&[key]).inc();
+}
+
+/** Count what happens to a poll, every `Poll` reports here */
+pub fn record_poll_event(bot: &str, event: &PollEvent) {
+    match event {
+        PollEvent::Opened => VOTE_WINDOWS_OPENED.with_label_values(&[bot]).inc(),
+        PollEvent::Closed { seconds_open } => {
+            VOTE_WINDOWS_CLOSED.with_label_values(&[bot]).inc();
+            VOTE_WINDOW_DURATION.with_label_values(&[bot]).observe(*seconds_open);
+        },
+        PollEvent::VoteCast { option } => {
+            let option = option.map(|i| (i + 1).to_string()).unwrap_or_else(|| "rating".to_owned());
+            VOTES_CAST.with_label_values(&[bot, &option]).inc();
+        },
+        PollEvent::VoteRejected(rejection) => VOTES_REJECTED.with_label_values(&[bot, rejection.describe()]).inc(),
+    }
+}
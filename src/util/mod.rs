@@ -1,3 +1,17 @@
 pub mod league;
 pub mod bot;
-pub mod twitch;
\ No newline at end of file
+pub mod twitch;
+pub mod log;
+pub mod outbox;
+pub mod metrics;
+pub mod command;
+pub mod tally;
+pub mod normalize;
+pub mod helix;
+pub mod eligibility;
+pub mod poll;
+pub mod history;
+pub mod audit;
+pub mod queue;
+pub mod lease;
+pub mod ignore;
\ No newline at end of file
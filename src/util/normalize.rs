@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use twitch_irc::message::PrivmsgMessage;
+use crate::util::twitch::strip_cheermotes;
+
+/** How much of a chat message has to look like a vote for it to count */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Strictness {
+    /** The whole message is the vote, only invisible characters are ignored */
+    Strict,
+    /** Emotes, punctuation, case and repeats are ignored, like `YES!! PogChamp` or `q q q` */
+    #[default]
+    Normal,
+    /** Like normal, but the first word is enough, like `yes I think so` */
+    Lenient,
+}
+
+impl Strictness {
+    /** Strictness for a keyword of the `!poll` command */
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_lowercase().as_str() {
+            "strict" => Some(Strictness::Strict),
+            "normal" => Some(Strictness::Normal),
+            "lenient" => Some(Strictness::Lenient),
+            _ => None,
+        }
+    }
+}
+
+/**
+Characters chat can't see, like the `U+E0000` tag chat clients append to send
+the same message twice.
+*/
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{E0000}'..='\u{E007F}' | '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}' | '\u{034F}' | '\u{00AD}')
+}
+
+/**
+Lowercase the text and drop everything that isn't a letter, a number or a
+space. `>` is kept for ranked ballots and `.`/`,` between numbers for ratings
+like `7.5` and ballots like `3,1,2`.
+*/
+pub fn normalize_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().filter(|&c| !is_invisible(c)).collect();
+    let mut cleaned = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        let between_digits = i > 0
+            && chars[i - 1].is_ascii_digit()
+            && matches!(chars.get(i + 1), Some(next) if next.is_ascii_digit());
+        if c.is_alphanumeric() || c == '>' || ((c == '.' || c == ',') && between_digits) {
+            cleaned.push(c);
+        } else {
+            cleaned.push(' ');
+        }
+    }
+    cleaned.to_lowercase().split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/** Text of the message without cheermotes and invisible characters */
+pub fn visible_text(msg: &PrivmsgMessage) -> String {
+    strip_cheermotes(msg).chars().filter(|&c| !is_invisible(c)).collect::<String>().trim().to_owned()
+}
+
+/**
+What a chat message could be voting for, most likely first. Bots take the first
+one that is a valid vote.
+*/
+pub fn vote_candidates(msg: &PrivmsgMessage, strictness: Strictness) -> Vec<String> {
+    let text = visible_text(msg);
+    if strictness == Strictness::Strict {
+        return vec![text];
+    }
+    let words: Vec<&str> = text.split_whitespace()
+        .filter(|word| !msg.emotes.iter().any(|emote| emote.code == *word))
+        .collect();
+    let normalized = normalize_text(&words.join(" "));
+    let normalized_words: Vec<&str> = normalized.split_whitespace().collect();
+    let mut candidates = Vec::new();
+    match normalized_words.first() {
+        // Spamming the same vote is still one vote
+        Some(first) if normalized_words.iter().all(|word| word == first) => candidates.push(first.to_string()),
+        Some(_) => candidates.push(normalized.clone()),
+        None => return candidates,
+    }
+    if strictness == Strictness::Lenient && normalized_words.len() > 1 {
+        candidates.push(normalized_words[0].to_owned());
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use twitch_irc::message::IRCMessage;
+
+    /** A chat message from a viewer, `emotes` is the IRC tag like `25:4-8` */
+    fn message(text: &str, emotes: &str) -> PrivmsgMessage {
+        let raw = [
+            "@badge-info=;badges=;color=;display-name=Viewer;emotes=", emotes,
+            ";flags=;id=1;mod=0;room-id=1;tmi-sent-ts=0;user-id=2 :viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :",
+            text,
+        ].concat();
+        PrivmsgMessage::try_from(IRCMessage::parse(&raw).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn invisible_characters_are_dropped() {
+        assert_eq!(normalize_text("yes \u{E0000}"), "yes");
+        assert_eq!(normalize_text("y\u{200B}es"), "yes");
+        assert_eq!(normalize_text("\u{FEFF}1\u{2060}"), "1");
+    }
+
+    #[test]
+    fn punctuation_and_case_are_dropped() {
+        assert_eq!(normalize_text("YES!!"), "yes");
+        assert_eq!(normalize_text("  Dust,  Mirage? "), "dust mirage");
+    }
+
+    #[test]
+    fn separators_between_numbers_are_kept() {
+        assert_eq!(normalize_text("3>1>2"), "3>1>2");
+        assert_eq!(normalize_text("7.5"), "7.5");
+        assert_eq!(normalize_text("3,1,2"), "3,1,2");
+        assert_eq!(normalize_text("7."), "7");
+        assert_eq!(normalize_text("a.b"), "a b");
+    }
+
+    #[test]
+    fn repeating_a_vote_is_one_vote() {
+        assert_eq!(vote_candidates(&message("q q q", ""), Strictness::Normal), vec!["q"]);
+        assert_eq!(vote_candidates(&message("yes!! YES", ""), Strictness::Normal), vec!["yes"]);
+    }
+
+    #[test]
+    fn emotes_are_dropped_by_their_code() {
+        assert_eq!(vote_candidates(&message("yes Kappa", "25:4-8"), Strictness::Normal), vec!["yes"]);
+        assert_eq!(vote_candidates(&message("Kappa", "25:0-4"), Strictness::Normal), Vec::<String>::new());
+        // Without the tag it's only a word
+        assert_eq!(vote_candidates(&message("yes Kappa", ""), Strictness::Normal), vec!["yes kappa"]);
+    }
+
+    #[test]
+    fn strict_takes_the_message_as_it_is() {
+        assert_eq!(vote_candidates(&message("YES!! \u{E0000}", ""), Strictness::Strict), vec!["YES!!"]);
+    }
+
+    #[test]
+    fn lenient_also_tries_the_first_word() {
+        assert_eq!(vote_candidates(&message("yes I think so", ""), Strictness::Lenient), vec!["yes i think so", "yes"]);
+        assert_eq!(vote_candidates(&message("yes I think so", ""), Strictness::Normal), vec!["yes i think so"]);
+        assert_eq!(vote_candidates(&message("yes", ""), Strictness::Lenient), vec!["yes"]);
+    }
+}
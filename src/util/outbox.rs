@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use twitch_irc::SecureTCPTransport;
+use twitch_irc::TwitchIRCClient;
+use twitch_irc::login::StaticLoginCredentials;
+
+use crate::util::{log, metrics};
+
+/** How many messages can wait to be sent before new ones get dropped */
+const CAPACITY: usize = 32;
+
+/** Twitch lets accounts that aren't mods send 20 messages every 30 seconds */
+const RATE_LIMIT: usize = 20;
+const RATE_WINDOW: Duration = Duration::from_secs(30);
+
+/** Share of the rate limit low priority messages can use, the rest is kept for the ones that matter */
+const LOW_PRIORITY_SHARE: usize = RATE_LIMIT / 2;
+/**
+Queue of messages going out to chat. Bots drop their messages in here and a
+single task sends them to the channel in order, slowing down before Twitch's
+rate limit.
+*/
+#[derive(Clone)]
+pub struct Outbox {
+    tx: mpsc::Sender<String>,
+    depth: Arc<AtomicUsize>,
+    /** When the messages of the last `RATE_WINDOW` were sent, oldest first */
+    sent: Arc<Mutex<VecDeque<Instant>>>,
+}
+
+impl Outbox {
+    /** Start the task sending the queued messages to `channel_name` */
+    pub fn start(client: TwitchIRCClient<SecureTCPTransport, StaticLoginCredentials>, channel_name: String) -> Self {
+        let (tx, mut rx) = mpsc::channel::<String>(CAPACITY);
+        let depth = Arc::new(AtomicUsize::new(0));
+        let sent = Arc::new(Mutex::new(VecDeque::new()));
+        let task_depth = depth.clone();
+        let task_sent = sent.clone();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                // Wait for the oldest message to leave the window if it's full
+                let wait = {
+                    let mut sent = task_sent.lock().unwrap();
+                    forget_old(&mut sent);
+                    match sent.front() {
+                        Some(&oldest) if sent.len() >= RATE_LIMIT => Some(RATE_WINDOW.saturating_sub(oldest.elapsed())),
+                        _ => None,
+                    }
+                };
+                if let Some(wait) = wait {
+                    sleep(wait).await;
+                }
+                task_depth.fetch_sub(1, Ordering::Relaxed);
+                task_sent.lock().unwrap().push_back(Instant::now());
+                if let Err(err) = client.say(channel_name.clone(), message).await {
+                    log::error(format!("[Outbox] Failed to send message\n{}", err));
+                }
+            }
+        });
+        Self { tx, depth, sent }
+    }
+    /** Queue a message for chat, it gets dropped if the queue is full */
+    pub fn say(&self, message: String) {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        metrics::OUTBOUND_QUEUED.inc();
+        if self.tx.try_send(message).is_err() {
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+            metrics::OUTBOUND_DROPPED.inc();
+            log::error("[Outbox] Queue is full, dropped a message".to_owned());
+        }
+    }
+    /**
+    Queue a message chat can do without, like progress updates. It's only
+    queued when nothing else is waiting and there's plenty of the rate limit
+    left, returns false if it wasn't.
+    */
+    pub fn say_low_priority(&self, message: String) -> bool {
+        let recently_sent = {
+            let mut sent = self.sent.lock().unwrap();
+            forget_old(&mut sent);
+            sent.len()
+        };
+        if self.depth() > 0 || recently_sent >= LOW_PRIORITY_SHARE {
+            return false;
+        }
+        self.say(message);
+        true
+    }
+    /** Amount of messages waiting to be sent */
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+}
+
+/** Drop the send times that left the rate limit window */
+fn forget_old(sent: &mut VecDeque<Instant>) {
+    while matches!(sent.front(), Some(at) if at.elapsed() >= RATE_WINDOW) {
+        sent.pop_front();
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::HashMap;
+use twitch_irc::message::{PrivmsgMessage, TwitchUserBasics};
+use crate::display::OptionView;
+use crate::util::eligibility::{Eligibility, EligibilityRules, Rejection, Rejections};
+use crate::util::helix::Helix;
+use crate::util::metrics;
+use crate::util::tally::{self, Outcome};
+
+/** Anything chat can vote for */
+pub trait Choice {
+    fn label(&self) -> String;
+}
+
+impl Choice for String {
+    fn label(&self) -> String {
+        self.clone()
+    }
+}
+
+pub struct PollOption<O> {
+    pub value: O,
+    /** Weighted total */
+    pub votes: i32,
+    /** How many viewers voted for it, no matter the weight */
+    pub voters: i32,
+}
+
+impl<O: Choice> PollOption<O> {
+    pub fn label(&self) -> String {
+        self.value.label()
+    }
+}
+
+/**
+What a viewer voted for, kept so the vote can be moved or taken back. Ranked
+ballots have the choices from most to least liked, approval ballots every
+option the viewer likes and plurality ballots only one. Rating ballots have
+no choices, only the rating.
+*/
+#[derive(Clone, PartialEq)]
+pub struct Ballot {
+    /** Login of the voter, for the history */
+    pub name: String,
+    pub choices: Vec<usize>,
+    pub amount: i32,
+    pub rating: Option<f64>,
+}
+
+/**
+How ballots turn into a tally and a result. The live tally counts the
+`counted` choices of every ballot and `outcome` decides who won.
+*/
+pub trait TallyStrategy {
+    /** Choices of a ballot that count in the live tally, only the favourite by default */
+    fn counted<'a>(&self, choices: &'a [usize]) -> &'a [usize] {
+        &choices[..choices.len().min(1)]
+    }
+    /** Who won, the most voted option by default */
+    fn outcome(&self, votes: &[i32], _ballots: &[&Ballot]) -> Outcome {
+        tally::outcome(votes)
+    }
+}
+
+/** One option per viewer, the most voted wins */
+pub struct Plurality;
+
+impl TallyStrategy for Plurality {}
+
+/** A result waiting out the confirmation window before the bot acts on it */
+pub struct Pending {
+    pub pick: usize,
+    /** Timestamp (ms) of when the window ends */
+    pub until: i64,
+}
+
+/** What a mod wants instead of a vetoed result */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Veto {
+    /** Vote again */
+    Rerun,
+    /** Go with the second most voted option */
+    RunnerUp,
+}
+
+impl Veto {
+    /** Veto for a keyword of the veto commands, running it again if there's none */
+    pub fn from_keyword(keyword: Option<&str>) -> Option<Self> {
+        match keyword.map(|keyword| keyword.to_lowercase()).as_deref() {
+            None | Some("rerun") | Some("again") => Some(Veto::Rerun),
+            Some("runner_up") | Some("runnerup") | Some("second") => Some(Veto::RunnerUp),
+            _ => None,
+        }
+    }
+}
+
+/** Something that happened to a poll */
+pub enum PollEvent {
+    Opened,
+    Closed { seconds_open: f64 },
+    /** For an option by its index, `None` for ratings */
+    VoteCast { option: Option<usize> },
+    VoteRejected(Rejection),
+}
+
+/** Gets told about every event of a poll, along with the name of the bot running it */
+pub type Listener = Box<dyn Fn(&str, &PollEvent) + Send + Sync>;
+
+/**
+The voting box every bot builds on: options, ballots, timing and who's allowed
+to vote. What the options are and how the result is decided is up to the bot.
+*/
+pub struct Poll<O> {
+    /** Name of the bot running the poll, for metrics and listeners */
+    pub bot: &'static str,
+    pub options: Vec<PollOption<O>>,
+    /** Voter id -> what they voted for */
+    pub voters: HashMap<String, Ballot>,
+    pub is_counting: bool,
+    pub opened_at: i64,
+    pub closed_at: i64,
+    /** Who opened and closed the poll, a chatter or whatever did it for them like the timer */
+    pub opened_by: String,
+    pub closed_by: String,
+    /** When the poll closes by itself, `None` if it waits for someone to close it */
+    pub closes_at: Option<i64>,
+    /** Timestamps to remind chat about the poll at, soonest first */
+    pub reminders: Vec<i64>,
+    /** Option that won a tie, picked by the bot */
+    pub decided: Option<usize>,
+    /** Only these options can be voted for, all of them if empty */
+    pub restricted_to: Vec<usize>,
+    pub eligibility: EligibilityRules,
+    pub rejections: Rejections,
+    /** Votes only get told to the listeners once the poll closes, they'd give the tally away */
+    pub secret: bool,
+    /** Votes waiting on Twitch to tell if their voter follows, the bot handles them again */
+    pub waiting: Vec<PrivmsgMessage>,
+    /** Result of the closed poll mods can still veto or override */
+    pub pending: Option<Pending>,
+    pub strategy: Box<dyn TallyStrategy + Send + Sync>,
+    listeners: Vec<Listener>,
+}
+
+impl<O: Choice> Poll<O> {
+    /** A closed plurality poll without options, with metrics listening to it */
+    pub fn new(bot: &'static str) -> Self {
+        let mut poll = Self {
+            bot,
+            options: Vec::new(),
+            voters: HashMap::new(),
+            is_counting: false,
+            opened_at: chrono::offset::Local::now().timestamp_millis(),
+            closed_at: 0,
+            opened_by: String::new(),
+            closed_by: String::new(),
+            closes_at: None,
+            reminders: Vec::new(),
+            decided: None,
+            restricted_to: Vec::new(),
+            eligibility: Default::default(),
+            rejections: Default::default(),
+            secret: false,
+            waiting: Vec::new(),
+            pending: None,
+            strategy: Box::new(Plurality),
+            listeners: Vec::new(),
+        };
+        poll.on_event(Box::new(metrics::record_poll_event));
+        poll
+    }
+    pub fn on_event(&mut self, listener: Listener) {
+        self.listeners.push(listener);
+    }
+    fn emit(&self, event: PollEvent) {
+        for listener in &self.listeners {
+            listener(self.bot, &event);
+        }
+    }
+    /** A `VoteCast` for every option the ballot counts for */
+    fn emit_vote(&self, ballot: &Ballot) {
+        if ballot.rating.is_some() {
+            return self.emit(PollEvent::VoteCast { option: None });
+        }
+        for &choice in self.strategy.counted(&ballot.choices) {
+            self.emit(PollEvent::VoteCast { option: Some(choice) });
+        }
+    }
+    /** Start counting from scratch with these options, `by` is who asked for it */
+    pub fn open(&mut self, options: Vec<O>, by: &str) {
+        self.options = options.into_iter()
+            .map(|value| PollOption { value, votes: 0, voters: 0 })
+            .collect();
+        self.voters = HashMap::new();
+        self.is_counting = true;
+        self.opened_at = chrono::offset::Local::now().timestamp_millis();
+        self.opened_by = by.to_owned();
+        self.closed_by = String::new();
+        self.closes_at = None;
+        self.reminders = Vec::new();
+        self.decided = None;
+        self.restricted_to = Vec::new();
+        self.rejections.clear();
+        self.waiting = Vec::new();
+        self.pending = None;
+        self.emit(PollEvent::Opened);
+    }
+    /**
+    Stop counting, the votes stay around for the results. Returns false if the
+    poll was already closed.
+    */
+    pub fn close(&mut self, by: &str) -> bool {
+        if !self.is_counting {
+            return false;
+        }
+        self.is_counting = false;
+        self.closed_at = chrono::offset::Local::now().timestamp_millis();
+        self.closed_by = by.to_owned();
+        if self.secret {
+            for ballot in self.voters.values() {
+                self.emit_vote(ballot);
+            }
+        }
+        self.emit(PollEvent::Closed { seconds_open: (self.closed_at - self.opened_at) as f64 / 1000.0 });
+        true
+    }
+    /**
+    Change when the poll closes and plan the reminders that are still ahead,
+    at fractions of the poll's length and at seconds before it closes.
+    */
+    pub fn set_closes_at(&mut self, closes_at: Option<i64>, fractions: &[f64], seconds_left: &[i64]) {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        self.closes_at = closes_at;
+        self.reminders = Vec::new();
+        if let Some(closes_at) = closes_at {
+            let length = closes_at - self.opened_at;
+            for fraction in fractions {
+                self.reminders.push(self.opened_at + (length as f64 * fraction) as i64);
+            }
+            for seconds in seconds_left {
+                self.reminders.push(closes_at - seconds * 1000);
+            }
+        }
+        self.reminders.retain(|&at| at > now);
+        self.reminders.sort_unstable();
+        self.reminders.dedup();
+    }
+    /** Seconds left before the poll closes by itself */
+    pub fn seconds_left(&self) -> Option<i64> {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        self.closes_at.map(|closes_at| ((closes_at - now).max(0) + 999) / 1000)
+    }
+    /** Returns true if the poll is open and its time is up */
+    pub fn is_due(&self, now: i64) -> bool {
+        self.is_counting && matches!(self.closes_at, Some(closes_at) if now >= closes_at)
+    }
+    /** Returns true if a reminder is due, several could be if the poll got shortened but one is enough */
+    pub fn take_due_reminder(&mut self, now: i64) -> bool {
+        if matches!(self.reminders.first(), Some(&at) if now >= at) {
+            self.reminders.retain(|&at| at > now);
+            return true;
+        }
+        false
+    }
+    /**
+    Returns true if a user can vote for these choices, `allow_change` lets
+    people who voted vote again.
+    */
+    pub fn can_vote(&self, voter: &TwitchUserBasics, choices: &[usize], allow_change: bool) -> bool {
+        self.is_counting
+            && (allow_change || !self.voters.contains_key(&voter.id))
+            && (self.restricted_to.is_empty() || choices.iter().all(|choice| self.restricted_to.contains(choice)))
+    }
+    /**
+    Check the sender against the eligibility rules, rejected votes get counted.
+    Votes Twitch is still being asked about go to `waiting` and don't count yet.
+    */
+    pub fn check_eligibility(&mut self, chatters: &Eligibility, msg: &PrivmsgMessage, helix: &Helix) -> bool {
+        match chatters.check(&self.eligibility, msg, self.opened_at, helix) {
+            Some(Ok(())) => true,
+            Some(Err(rejection)) => {
+                if self.rejections.add(&msg.sender, rejection) {
+                    self.emit(PollEvent::VoteRejected(rejection));
+                }
+                false
+            },
+            None => {
+                self.waiting.push(msg.clone());
+                false
+            },
+        }
+    }
+    /**
+    Add a vote to the box, `choices` are indexes of the options. If the voter
+    already voted their previous vote gets moved and if they got rejected
+    before the rejection is forgotten, the vote got in after all.
+    */
+    pub fn add_vote(&mut self, amount: i32, choices: Vec<usize>, voter: &TwitchUserBasics) {
+        if choices.is_empty() || choices.iter().any(|&choice| choice >= self.options.len()) {
+            return;
+        }
+        self.remove_vote(voter);
+        self.rejections.remove(voter);
+        let counted = self.strategy.counted(&choices).to_vec();
+        for choice in counted {
+            let option = &mut self.options[choice];
+            option.votes += amount;
+            option.voters += 1;
+        }
+        let ballot = Ballot { name: voter.login.clone(), choices, amount, rating: None };
+        if !self.secret {
+            self.emit_vote(&ballot);
+        }
+        self.voters.insert(voter.id.clone(), ballot);
+    }
+    /** Add a rating to the box, replacing the voter's previous one and forgetting any rejection */
+    pub fn add_rating(&mut self, rating: f64, voter: &TwitchUserBasics) {
+        self.remove_vote(voter);
+        self.rejections.remove(voter);
+        let ballot = Ballot { name: voter.login.clone(), choices: Vec::new(), amount: 1, rating: Some(rating) };
+        if !self.secret {
+            self.emit_vote(&ballot);
+        }
+        self.voters.insert(voter.id.clone(), ballot);
+    }
+    /** Take a voter's vote out of the box, returns false if they hadn't voted */
+    pub fn remove_vote(&mut self, voter: &TwitchUserBasics) -> bool {
+        match self.voters.remove(&voter.id) {
+            Some(ballot) => {
+                for &choice in self.strategy.counted(&ballot.choices) {
+                    if let Some(option) = self.options.get_mut(choice) {
+                        option.votes -= ballot.amount;
+                        option.voters -= 1;
+                    }
+                }
+                true
+            },
+            None => false,
+        }
+    }
+    /** Hold a result for `seconds` so mods get a chance to veto it */
+    pub fn hold(&mut self, pick: usize, seconds: i64) {
+        let now = chrono::offset::Local::now().timestamp_millis();
+        self.pending = Some(Pending { pick, until: now + seconds * 1000 });
+    }
+    /** The held result once its window is over, nobody vetoed it */
+    pub fn take_confirmed(&mut self, now: i64) -> Option<usize> {
+        match &self.pending {
+            Some(pending) if now >= pending.until => self.pending.take().map(|pending| pending.pick),
+            _ => None,
+        }
+    }
+    /** Most voted option after `pick`, `None` if nobody voted for another one */
+    pub fn runner_up(&self, pick: usize) -> Option<usize> {
+        (0..self.options.len())
+            .filter(|&i| i != pick && self.options[i].votes > 0)
+            .max_by_key(|&i| (self.options[i].votes, std::cmp::Reverse(i)))
+    }
+    /** The option called `text`, by its label or its number starting at 1 */
+    pub fn option_named(&self, text: &str) -> Option<usize> {
+        let text = text.trim();
+        if let Ok(number) = text.parse::<usize>() {
+            return number.checked_sub(1).filter(|&i| i < self.options.len());
+        }
+        self.options.iter().position(|option| option.label().eq_ignore_ascii_case(text))
+    }
+    /** Weighted total of every option, in order */
+    pub fn votes(&self) -> Vec<i32> {
+        self.options.iter().map(|option| option.votes).collect()
+    }
+    /** Options from most to least voted, ties keep the order they were given in */
+    pub fn ranked(&self) -> Vec<&PollOption<O>> {
+        let mut ranked: Vec<&PollOption<O>> = self.options.iter().collect();
+        ranked.sort_by_key(|option| std::cmp::Reverse(option.votes));
+        ranked
+    }
+    /** Who won by the tally strategy, before any tie gets broken */
+    pub fn outcome(&self) -> Outcome {
+        let ballots: Vec<&Ballot> = self.voters.values().collect();
+        self.strategy.outcome(&self.votes(), &ballots)
+    }
+    /** Index of the winning option or the one a tie was decided for, `None` for nobody voting and unbroken ties */
+    pub fn winner_index(&self) -> Option<usize> {
+        if self.decided.is_some() {
+            return self.decided;
+        }
+        match self.outcome() {
+            Outcome::Winner(winner) => Some(winner),
+            _ => None,
+        }
+    }
+    /** Like `Dust and Mirage` */
+    pub fn labels_of(&self, indexes: &[usize]) -> String {
+        let labels: Vec<String> = indexes.iter()
+            .filter_map(|&i| self.options.get(i))
+            .map(|option| option.label())
+            .collect();
+        labels.join(" and ")
+    }
+    /** The options as the displays show them */
+    pub fn option_views(&self) -> Vec<OptionView> {
+        self.options.iter()
+            .map(|option| OptionView { label: option.label(), votes: option.votes, voters: option.voters })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(name: &str) -> TwitchUserBasics {
+        TwitchUserBasics { id: name.to_owned(), login: name.to_owned(), name: name.to_owned() }
+    }
+
+    fn open_poll(labels: &[&str]) -> Poll<String> {
+        let mut poll = Poll::new("VoteBot");
+        poll.open(labels.iter().map(|label| label.to_string()).collect(), "someone");
+        poll
+    }
+
+    #[test]
+    fn changing_a_vote_moves_it_to_the_new_option() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.add_vote(2, vec![0], &viewer("alice"));
+        poll.add_vote(1, vec![0], &viewer("bob"));
+        assert_eq!(poll.votes(), vec![3, 0]);
+        poll.add_vote(2, vec![1], &viewer("alice"));
+        assert_eq!(poll.votes(), vec![1, 2]);
+        assert_eq!(poll.options[0].voters, 1);
+        assert_eq!(poll.options[1].voters, 1);
+        assert_eq!(poll.voters.len(), 2);
+    }
+
+    #[test]
+    fn taking_a_vote_back_takes_its_weight() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.add_vote(3, vec![1], &viewer("alice"));
+        assert!(poll.remove_vote(&viewer("alice")));
+        assert!(!poll.remove_vote(&viewer("alice")));
+        assert_eq!(poll.votes(), vec![0, 0]);
+        assert_eq!(poll.options[1].voters, 0);
+    }
+
+    #[test]
+    fn votes_for_options_that_dont_exist_are_ignored() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.add_vote(1, vec![0], &viewer("alice"));
+        poll.add_vote(1, vec![2], &viewer("alice"));
+        poll.add_vote(1, Vec::new(), &viewer("bob"));
+        assert_eq!(poll.votes(), vec![1, 0]);
+        assert_eq!(poll.voters.len(), 1);
+    }
+
+    #[test]
+    fn accepted_votes_are_no_longer_rejected() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.rejections.add(&viewer("alice"), Rejection::NotFollower);
+        poll.rejections.add(&viewer("bob"), Rejection::NotFollower);
+        poll.rejections.add(&viewer("carol"), Rejection::NotSubscriber);
+        poll.add_vote(1, vec![0], &viewer("alice"));
+        poll.add_rating(5.0, &viewer("carol"));
+        assert_eq!(poll.rejections.summary().as_deref(), Some("1 vote rejected: 1 not following"));
+    }
+
+    #[test]
+    fn voting_again_needs_allow_change() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        let alice = viewer("alice");
+        assert!(poll.can_vote(&alice, &[0], false));
+        poll.add_vote(1, vec![0], &alice);
+        assert!(!poll.can_vote(&alice, &[1], false));
+        assert!(poll.can_vote(&alice, &[1], true));
+        poll.close("someone");
+        assert!(!poll.can_vote(&alice, &[1], true));
+        assert!(!poll.can_vote(&viewer("bob"), &[1], true));
+    }
+
+    #[test]
+    fn restricted_polls_only_take_the_allowed_options() {
+        let mut poll = open_poll(&["Dust", "Mirage", "Inferno"]);
+        poll.restricted_to = vec![0, 2];
+        let alice = viewer("alice");
+        assert!(poll.can_vote(&alice, &[0], false));
+        assert!(poll.can_vote(&alice, &[2, 0], false));
+        assert!(!poll.can_vote(&alice, &[1], false));
+        assert!(!poll.can_vote(&alice, &[0, 1], false));
+    }
+
+    #[test]
+    fn the_most_voted_option_wins() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        assert!(matches!(poll.outcome(), Outcome::NoVotes));
+        assert_eq!(poll.winner_index(), None);
+        poll.add_vote(1, vec![0], &viewer("alice"));
+        poll.add_vote(1, vec![1], &viewer("bob"));
+        poll.add_vote(1, vec![1], &viewer("carol"));
+        assert!(matches!(poll.outcome(), Outcome::Winner(1)));
+        assert_eq!(poll.winner_index(), Some(1));
+    }
+
+    #[test]
+    fn ties_have_no_winner_until_decided() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.add_vote(1, vec![0], &viewer("alice"));
+        poll.add_vote(1, vec![1], &viewer("bob"));
+        assert!(matches!(poll.outcome(), Outcome::Tie(ref tied) if tied == &vec![0, 1]));
+        assert_eq!(poll.winner_index(), None);
+        poll.decided = Some(0);
+        assert_eq!(poll.winner_index(), Some(0));
+    }
+
+    #[test]
+    fn held_results_are_confirmed_once_the_window_is_over() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        let now = chrono::offset::Local::now().timestamp_millis();
+        poll.hold(1, 30);
+        let until = poll.pending.as_ref().unwrap().until;
+        assert!(until >= now + 30_000);
+        assert_eq!(poll.take_confirmed(until - 1), None);
+        assert!(poll.pending.is_some());
+        assert_eq!(poll.take_confirmed(until), Some(1));
+        assert!(poll.pending.is_none());
+        assert_eq!(poll.take_confirmed(until + 1), None);
+    }
+
+    #[test]
+    fn opening_a_poll_drops_the_held_result() {
+        let mut poll = open_poll(&["Dust", "Mirage"]);
+        poll.hold(0, 0);
+        poll.open(vec!["Nuke".to_owned()], "someone");
+        assert!(poll.pending.is_none());
+        assert_eq!(poll.take_confirmed(i64::MAX), None);
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+/** A poll waiting for its turn, `poll` is what would follow `!poll` in chat */
+#[derive(Serialize, Clone)]
+pub struct QueuedPoll {
+    pub question: String,
+    pub poll: String,
+    pub added_by: String,
+}
+
+/**
+Polls lined up to start one after the other. It's shared between chat, the
+admin API and the dashboard, positions start at 1 like they do in chat.
+*/
+#[derive(Clone, Default)]
+pub struct PollQueue {
+    polls: Arc<Mutex<VecDeque<QueuedPoll>>>,
+}
+
+impl PollQueue {
+    /** Add a poll at the end, returns its position */
+    pub fn push(&self, poll: QueuedPoll) -> usize {
+        let mut polls = self.polls.lock().unwrap();
+        polls.push_back(poll);
+        polls.len()
+    }
+    /** Add a poll that goes before every other one */
+    pub fn push_front(&self, poll: QueuedPoll) {
+        self.polls.lock().unwrap().push_front(poll);
+    }
+    /** Take the next poll out */
+    pub fn pop(&self) -> Option<QueuedPoll> {
+        self.polls.lock().unwrap().pop_front()
+    }
+    pub fn remove(&self, position: usize) -> Option<QueuedPoll> {
+        self.polls.lock().unwrap().remove(position.checked_sub(1)?)
+    }
+    /** Move a poll to another position, returns false if either is out of the queue */
+    pub fn move_to(&self, from: usize, to: usize) -> bool {
+        let mut polls = self.polls.lock().unwrap();
+        if from == 0 || to == 0 || from > polls.len() || to > polls.len() {
+            return false;
+        }
+        let poll = polls.remove(from - 1).unwrap();
+        polls.insert(to - 1, poll);
+        true
+    }
+    /** Empty the queue, returns how many polls were in it */
+    pub fn clear(&self) -> usize {
+        let mut polls = self.polls.lock().unwrap();
+        let count = polls.len();
+        polls.clear();
+        count
+    }
+    pub fn contains(&self, poll: &str) -> bool {
+        self.polls.lock().unwrap().iter().any(|queued| queued.poll == poll)
+    }
+    /** Every queued poll, next one first */
+    pub fn list(&self) -> Vec<QueuedPoll> {
+        self.polls.lock().unwrap().iter().cloned().collect()
+    }
+}
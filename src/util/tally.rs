@@ -0,0 +1,247 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/** What to do when the most voted options are tied */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TiePolicy {
+    /** Pick one of the tied options at random */
+    #[default]
+    Random,
+    /** Vote again between the tied options, a second tie is picked at random */
+    Runoff,
+    /** Nobody wins until the streamer picks one */
+    StreamerDecides,
+    /** Go with the configured default option */
+    Default,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Outcome {
+    Winner(usize),
+    /** Indexes of the options tied for first */
+    Tie(Vec<usize>),
+    NoVotes,
+}
+
+/** Who won a plain tally, ties and polls nobody voted on are told apart */
+pub fn outcome(votes: &[i32]) -> Outcome {
+    let max = votes.iter().copied().max().unwrap_or(0);
+    if max <= 0 {
+        return Outcome::NoVotes;
+    }
+    let tied: Vec<usize> = (0..votes.len()).filter(|&i| votes[i] == max).collect();
+    match tied.as_slice() {
+        [winner] => Outcome::Winner(*winner),
+        _ => Outcome::Tie(tied),
+    }
+}
+
+/** One of the tied options at random */
+pub fn pick_random(tied: &[usize]) -> Option<usize> {
+    tied.choose(&mut rand::thread_rng()).copied()
+}
+
+/** One counting round of an instant runoff */
+pub struct Round {
+    /** Votes of every option this round, eliminated options have 0 */
+    pub tallies: Vec<i32>,
+    /** Option that got eliminated at the end of the round */
+    pub eliminated: Option<usize>,
+}
+
+pub struct Runoff {
+    pub rounds: Vec<Round>,
+    /** `None` when nobody voted or the last options are tied */
+    pub winner: Option<usize>,
+}
+
+/**
+Instant runoff over ranked ballots. Each ballot is a list of option indexes
+from most to least liked and how much it's worth. Every round each ballot counts
+for its favourite option still standing, an option with more than half the
+votes wins and otherwise the least voted option is eliminated. When several
+options are tied for last the one given last in the poll goes first.
+*/
+pub fn instant_runoff(option_count: usize, ballots: &[(&[usize], i32)]) -> Runoff {
+    let mut active = vec![true; option_count];
+    let mut rounds = Vec::new();
+    loop {
+        let mut tallies = vec![0; option_count];
+        for (ranking, amount) in ballots {
+            if let Some(&choice) = ranking.iter().find(|&&choice| choice < option_count && active[choice]) {
+                tallies[choice] += amount;
+            }
+        }
+        let total: i32 = tallies.iter().sum();
+        let remaining: Vec<usize> = (0..option_count).filter(|&i| active[i]).collect();
+
+        let majority = remaining.iter().copied().find(|&i| tallies[i] * 2 > total);
+        if total == 0 || majority.is_some() || remaining.len() <= 1 {
+            let winner = if total == 0 { None } else { majority.or_else(|| remaining.first().copied()) };
+            rounds.push(Round { tallies, eliminated: None });
+            return Runoff { rounds, winner };
+        }
+
+        let lowest = remaining.iter().map(|&i| tallies[i]).min().unwrap_or(0);
+        if remaining.iter().all(|&i| tallies[i] == lowest) {
+            // Everyone left is tied, eliminating any of them would be arbitrary
+            rounds.push(Round { tallies, eliminated: None });
+            return Runoff { rounds, winner: None };
+        }
+        let eliminated = remaining.iter().rev().copied().find(|&i| tallies[i] == lowest);
+        if let Some(eliminated) = eliminated {
+            active[eliminated] = false;
+        }
+        rounds.push(Round { tallies, eliminated });
+    }
+}
+
+/** A bar of a histogram, like every rating from 7 to 7.9 */
+pub struct Bucket {
+    pub label: String,
+    pub count: i32,
+}
+
+pub struct RatingStats {
+    pub count: usize,
+    /** `None` until someone rates */
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub buckets: Vec<Bucket>,
+}
+
+/**
+Count, mean, median and distribution of ratings between `min` and `max`.
+Small whole ranges like 1 to 10 get a bucket per number (7.5 goes with the 7s),
+anything else is split into 10 buckets of the same size.
+*/
+pub fn rating_stats(ratings: &[f64], min: f64, max: f64) -> RatingStats {
+    let mut sorted = ratings.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let count = sorted.len();
+    let mean = if count > 0 { Some(sorted.iter().sum::<f64>() / count as f64) } else { None };
+    let median = match count {
+        0 => None,
+        _ if count % 2 == 1 => Some(sorted[count / 2]),
+        _ => Some((sorted[count / 2 - 1] + sorted[count / 2]) / 2.0),
+    };
+
+    let whole = min.fract() == 0.0 && max.fract() == 0.0 && max - min < 10.5;
+    let (bucket_count, width) = if whole {
+        ((max - min) as usize + 1, 1.0)
+    } else {
+        (10, (max - min) / 10.0)
+    };
+    let mut buckets: Vec<Bucket> = (0..bucket_count)
+        .map(|i| {
+            let low = min + width * i as f64;
+            let label = if whole {
+                format_number(low)
+            } else {
+                [format_number(low), "-".to_owned(), format_number(low + width)].concat()
+            };
+            Bucket { label, count: 0 }
+        })
+        .collect();
+    if width > 0.0 {
+        for rating in &sorted {
+            let i = (((rating - min) / width) as usize).min(bucket_count - 1);
+            buckets[i].count += 1;
+        }
+    }
+    RatingStats { count, mean, median, buckets }
+}
+
+/** `7`, `7.5` or `7.3`, never more than one decimal */
+pub fn format_number(number: f64) -> String {
+    let rounded = (number * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        (rounded as i64).to_string()
+    } else {
+        format!("{:.1}", rounded)
+    }
+}
+
+/** One line ASCII histogram for chat, like `6:## 7:#### 8:######`, bars are at most 10 long */
+pub fn ascii_histogram(buckets: &[Bucket]) -> String {
+    let highest = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0).max(1);
+    let bars: Vec<String> = buckets.iter()
+        .map(|bucket| {
+            let length = ((bucket.count * 10 + highest - 1) / highest) as usize;
+            [bucket.label.clone(), ":".to_owned(), "#".repeat(length)].concat()
+        })
+        .collect();
+    bars.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runoff_moves_eliminated_votes_to_the_next_choice() {
+        let (first, second, third): (&[usize], &[usize], &[usize]) = (&[0, 1], &[1, 0], &[2, 1]);
+        let runoff = instant_runoff(3, &[(first, 4), (second, 3), (third, 2)]);
+        assert_eq!(runoff.rounds.len(), 2);
+        assert_eq!(runoff.rounds[0].tallies, vec![4, 3, 2]);
+        assert_eq!(runoff.rounds[0].eliminated, Some(2));
+        assert_eq!(runoff.rounds[1].tallies, vec![4, 5, 0]);
+        assert_eq!(runoff.winner, Some(1));
+    }
+
+    #[test]
+    fn runoff_eliminates_the_last_option_of_a_tie_for_last() {
+        let ballots: Vec<(&[usize], i32)> = vec![(&[0], 2), (&[3], 2), (&[1], 1), (&[2], 1)];
+        let runoff = instant_runoff(4, &ballots);
+        assert_eq!(runoff.rounds[0].eliminated, Some(2));
+        assert_eq!(runoff.rounds[1].eliminated, Some(1));
+    }
+
+    #[test]
+    fn runoff_has_no_winner_when_everyone_left_is_tied() {
+        let ballots: Vec<(&[usize], i32)> = vec![(&[0], 1), (&[1], 1)];
+        let runoff = instant_runoff(2, &ballots);
+        assert_eq!(runoff.rounds.len(), 1);
+        assert_eq!(runoff.rounds[0].eliminated, None);
+        assert_eq!(runoff.winner, None);
+    }
+
+    #[test]
+    fn runoff_without_ballots_has_no_winner() {
+        let runoff = instant_runoff(3, &[]);
+        assert_eq!(runoff.winner, None);
+    }
+
+    #[test]
+    fn whole_ratings_get_a_bucket_per_number_edges_included() {
+        let stats = rating_stats(&[1.0, 7.5, 10.0], 1.0, 10.0);
+        let labels: Vec<&str> = stats.buckets.iter().map(|bucket| bucket.label.as_str()).collect();
+        assert_eq!(labels, vec!["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]);
+        assert_eq!(stats.buckets[0].count, 1);
+        assert_eq!(stats.buckets[6].count, 1);
+        assert_eq!(stats.buckets[9].count, 1);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.median, Some(7.5));
+    }
+
+    #[test]
+    fn other_ratings_get_ten_buckets_and_the_maximum_goes_in_the_last() {
+        let stats = rating_stats(&[0.0, 19.9, 20.0], 0.0, 20.0);
+        assert_eq!(stats.buckets.len(), 10);
+        assert_eq!(stats.buckets[0].label, "0-2");
+        assert_eq!(stats.buckets[9].label, "18-20");
+        assert_eq!(stats.buckets[0].count, 1);
+        assert_eq!(stats.buckets[9].count, 2);
+        assert_eq!(stats.median, Some(19.9));
+    }
+
+    #[test]
+    fn nobody_rating_has_no_mean_or_median() {
+        let stats = rating_stats(&[], 1.0, 5.0);
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.mean, None);
+        assert_eq!(stats.median, None);
+        assert!(stats.buckets.iter().all(|bucket| bucket.count == 0));
+    }
+}
@@ -8,4 +8,37 @@ pub fn is_mod(msg: &PrivmsgMessage/* , sender: &TwitchUserBasics */) -> bool {
         }
     }
     false
+}
+
+/** Returns true if the sender has the badge, like `"vip"` or `"subscriber"` */
+pub fn has_badge(msg: &PrivmsgMessage, name: &str) -> bool {
+    msg.badges.iter().any(|badge| badge.name == name)
+}
+
+/**
+Tier of the sender's subscription (1, 2 or 3). The subscriber badge's version
+is the amount of months for tier 1 and 2000/3000 + months for tier 2/3.
+*/
+pub fn subscriber_tier(msg: &PrivmsgMessage) -> Option<u8> {
+    let badge = msg.badges.iter().find(|badge| badge.name == "subscriber")?;
+    match badge.version.parse::<u32>().unwrap_or(0) {
+        3000..=3999 => Some(3),
+        2000..=2999 => Some(2),
+        _ => Some(1),
+    }
+}
+
+/** Remove the cheermotes (`Cheer100`, `Kappa50`...) from a message with bits */
+pub fn strip_cheermotes(msg: &PrivmsgMessage) -> String {
+    if msg.bits.is_none() {
+        return msg.message_text.clone();
+    }
+    msg.message_text.split_whitespace()
+        .filter(|word| {
+            let letters = word.trim_end_matches(|c: char| c.is_ascii_digit());
+            // Needs both the name and the amount to be a cheermote
+            letters.is_empty() || letters.len() == word.len() || !letters.chars().all(|c| c.is_alphabetic())
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
 }
\ No newline at end of file